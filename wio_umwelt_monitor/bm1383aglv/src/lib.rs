@@ -5,13 +5,17 @@
 use panic_halt as _;
 use wio_terminal as wio;
 
-use wio::hal::gpio::*;
-use wio::hal::sercom::*;
 use wio::prelude::*;
 use wio::hal::delay::Delay;
+use wio::hal::hal::blocking::i2c::{Write, WriteRead};
+use units::*;
 
 
-pub struct BM1383AGLV {
+pub const BM1383AGLV_ADDRESS: u8 = 0x5D;
+pub const BM1383AGLV_ID: u8 = 0x32;
+
+pub struct BM1383AGLV<I2C> {
+    i2c: I2C,
     enable: bool,
 }
 
@@ -27,36 +31,40 @@ pub enum ErrorBM1383AGLV {
     NoData
 }
 
-impl BM1383AGLV {
-    pub fn new() -> BM1383AGLV {
+impl<I2C> BM1383AGLV<I2C>
+where
+    I2C: Write + WriteRead
+{
+    pub fn new(i2c: I2C) -> BM1383AGLV<I2C> {
         BM1383AGLV {
+            i2c,
             enable: false
         }
     }
 
-    pub fn init(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, delay: &mut Delay) -> Result<(), ErrorBM1383AGLV> {
+    pub fn init(&mut self, delay: &mut Delay) -> Result<(), ErrorBM1383AGLV> {
 
-        match self.read_single(i2c, 0x10) {
+        match self.read_id() {
             Ok(reg) => {
-                if reg != 0x32 {
+                if reg != BM1383AGLV_ID {
                     return Err(ErrorBM1383AGLV::CanNotFind);
                 }
             },
             Err(_) => return Err(ErrorBM1383AGLV::CanNotAccess)
         }
 
-        if let Err(_) = self.write_single(i2c, 0x12, 1) {
+        if let Err(_) = self.write_single(0x12, 1) {
             return Err(ErrorBM1383AGLV::CanNotWritePowDwn);
         }
 
         // wait between power down and reset
         delay.delay_ms(2u16);
 
-        if let Err(_) = self.write_single(i2c, 0x13, 1) {
+        if let Err(_) = self.write_single(0x13, 1) {
             return Err(ErrorBM1383AGLV::CanNotWriteReset);
         }
 
-        if let Err(_) = self.write_single(i2c, 0x14, 0xCA) {
+        if let Err(_) = self.write_single(0x14, 0xCA) {
             return Err(ErrorBM1383AGLV::CanNotWriteModeCtr);
         }
 
@@ -66,14 +74,18 @@ impl BM1383AGLV {
         Ok(())
     }
 
-    pub fn get_value(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>) -> Result<(f32, f32), ErrorBM1383AGLV> {
+    pub fn read_id(&mut self) -> Result<u8, ErrorBM1383AGLV> {
+        self.read_single(0x10)
+    }
+
+    pub fn get_value(&mut self) -> Result<(Celsius, Hectopascal), ErrorBM1383AGLV> {
 
         if !self.enable {
             return Err(ErrorBM1383AGLV::NotInitialized);
         }
 
         let mut val :[u8; 5] = [0; 5];
-        self.get_rawval(i2c, &mut val)?;
+        self.get_rawval(&mut val)?;
 
         if val[0] == 0 && val[1] == 0 && val[2] == 0 && val[3] == 0 && val[4] == 0 {
             return Err(ErrorBM1383AGLV::NoData);
@@ -87,27 +99,27 @@ impl BM1383AGLV {
         let rawtemp = (value[3] * 256) + value[4];
         let temp = (rawtemp as f32) / 32.0;
 
-        Ok((temp, press))
+        Ok((Celsius(temp), Hectopascal(press)))
     }
 
-    fn get_rawval(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, data: &mut [u8]) -> Result<(), ErrorBM1383AGLV> {
-        match i2c.write_read(0x5D, &[0x1A], data) {
+    fn get_rawval(&mut self, data: &mut [u8]) -> Result<(), ErrorBM1383AGLV> {
+        match self.i2c.write_read(BM1383AGLV_ADDRESS, &[0x1A], data) {
             Ok(_) => Ok(()),
             _ => Err(ErrorBM1383AGLV::ReadFailure)
         }
     }
 
-    fn write_single(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, memory_address: u8, data: u8) -> Result<(), ErrorBM1383AGLV> {
+    fn write_single(&mut self, memory_address: u8, data: u8) -> Result<(), ErrorBM1383AGLV> {
         let send_data :[u8; 2] = [memory_address, data];
-        match i2c.write(0x5D, &send_data) {
+        match self.i2c.write(BM1383AGLV_ADDRESS, &send_data) {
             Ok(_) => Ok(()),
             _ => Err(ErrorBM1383AGLV::WriteFailure)
         }
     }
 
-    fn read_single(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, memory_address: u8) -> Result<u8, ErrorBM1383AGLV> {
+    fn read_single(&mut self, memory_address: u8) -> Result<u8, ErrorBM1383AGLV> {
         let mut recv_data: [u8; 1] = [0];
-        match i2c.write_read(0x5D, &[memory_address], &mut recv_data) {
+        match self.i2c.write_read(BM1383AGLV_ADDRESS, &[memory_address], &mut recv_data) {
             Ok(_) => Ok(recv_data[0]),
             _ => Err(ErrorBM1383AGLV::ReadFailure)
         }
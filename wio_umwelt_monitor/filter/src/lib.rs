@@ -0,0 +1,198 @@
+//! spike and outlier filters for sensor readings
+
+#![no_std]
+
+
+// 中央値をとる値の数の上限
+pub const MAX_MEDIAN: usize = 9;
+// 変化率の制限でこの回数続けて棄却したら、その値を新しい水準として受け付ける
+// （窓を開けたときなどの本当の急変を捨て続けないため）
+const MAX_CONSECUTIVE_REJECTS: u8 = 3;
+
+// 1チャネル分のフィルタの設定（各段はNoneか1で使わない）
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FilterConfig {
+    // 妥当な値の範囲（範囲外は棄却する）
+    pub range: Option<(f32, f32)>,
+    // 1分あたりの変化量の上限（超えたら棄却する）
+    pub max_rate: Option<f32>,
+    // 直近いくつの値の中央値をとるか（MAX_MEDIANまで）
+    pub median: usize,
+    // 指数移動平均の時定数[s]
+    pub smoothing: Option<f32>
+}
+
+// 値を棄却した理由
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Rejection {
+    OutOfRange,
+    TooFast
+}
+
+// 妥当性の範囲、変化率の制限、中央値、指数移動平均の順に通すフィルタ
+pub struct FilterChain {
+    config: FilterConfig,
+    window: [f32; MAX_MEDIAN],
+    window_len: usize,
+    window_next: usize,
+    // 最後に受け付けた値とその時刻[s]
+    last: Option<(u32, f32)>,
+    smoothed: Option<(u32, f32)>,
+    consecutive: u8,
+    rejected: u32
+}
+
+// 測定中で値を返さないセンサの代わりに、そのセンサが最後に返した値を保つ
+// （欠測にすると後段のフィルタや解析の続きが途切れるため）
+// 同じ種類の値を測るセンサが複数あっても混ざらないように、センサごとに保つ
+pub struct SampleHold<T, const N: usize> {
+    last: [Option<T>; N]
+}
+
+impl FilterConfig {
+    // 何もしない設定
+    pub const NONE: FilterConfig = FilterConfig {
+        range: None,
+        max_rate: None,
+        median: 1,
+        smoothing: None
+    };
+}
+
+impl FilterChain {
+    pub const fn new(config: FilterConfig) -> FilterChain {
+        FilterChain {
+            config,
+            window: [0.0; MAX_MEDIAN],
+            window_len: 0,
+            window_next: 0,
+            last: None,
+            smoothed: None,
+            consecutive: 0,
+            rejected: 0
+        }
+    }
+
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    // これまでに棄却した値の数
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+
+    // 棄却した数以外の状態を捨てる
+    pub fn reset(&mut self) {
+        self.window_len = 0;
+        self.window_next = 0;
+        self.last = None;
+        self.smoothed = None;
+        self.consecutive = 0;
+    }
+
+    // timestampは[s]、欠測は欠測のまま返す
+    pub fn update(&mut self, timestamp: u32, value: Option<f32>) -> Result<Option<f32>, Rejection> {
+        let value = match value {
+            Some(value) if !value.is_nan() => value,
+            _ => return Ok(None)
+        };
+
+        if let Some((min, max)) = self.config.range {
+            if value < min || max < value {
+                self.rejected += 1;
+                return Err(Rejection::OutOfRange);
+            }
+        }
+
+        if let (Some(max_rate), Some((last_timestamp, last_value))) = (self.config.max_rate, self.last) {
+            let minutes = timestamp.wrapping_sub(last_timestamp).max(1) as f32 / 60.0;
+
+            if max_rate * minutes < (value - last_value).abs() {
+                self.consecutive += 1;
+
+                if self.consecutive < MAX_CONSECUTIVE_REJECTS {
+                    self.rejected += 1;
+                    return Err(Rejection::TooFast);
+                }
+
+                // 急変が続いたので、以前の値を捨ててこの値から始め直す
+                self.reset();
+            }
+        }
+        self.consecutive = 0;
+        self.last = Some((timestamp, value));
+
+        let value = self.median(value);
+        Ok(Some(self.smooth(timestamp, value)))
+    }
+
+    // 直近の受け付けた値の中央値（偶数個のときは中央の2つの平均）
+    fn median(&mut self, value: f32) -> f32 {
+        let size = self.config.median.clamp(1, MAX_MEDIAN);
+        if size == 1 {
+            return value;
+        }
+
+        self.window[self.window_next] = value;
+        self.window_next = (self.window_next + 1) % size;
+        self.window_len = (self.window_len + 1).min(size);
+
+        let mut sorted = [0.0; MAX_MEDIAN];
+        let sorted = &mut sorted[..self.window_len];
+        sorted.copy_from_slice(&self.window[..self.window_len]);
+        sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+
+        let middle = sorted.len() / 2;
+        if sorted.len() % 2 == 1 {
+            sorted[middle]
+        }
+        else {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        }
+    }
+
+    fn smooth(&mut self, timestamp: u32, value: f32) -> f32 {
+        let time_constant = match self.config.smoothing {
+            Some(time_constant) => time_constant,
+            None => return value
+        };
+
+        let smoothed = match self.smoothed {
+            Some((last_timestamp, last_value)) => {
+                let elapsed = timestamp.wrapping_sub(last_timestamp) as f32;
+                let alpha = elapsed / (time_constant + elapsed);
+                last_value + alpha * (value - last_value)
+            },
+            None => value
+        };
+
+        self.smoothed = Some((timestamp, smoothed));
+        smoothed
+    }
+}
+
+impl<T: Clone, const N: usize> SampleHold<T, N> {
+    pub fn new() -> SampleHold<T, N> {
+        SampleHold {
+            last: core::array::from_fn(|_| None)
+        }
+    }
+
+    // sourceの番号のセンサが返した値（測定中ならNone）を受け取り、代わりに使う値を返す
+    // 一度も値を返していないセンサはNoneのまま
+    pub fn update(&mut self, source: usize, value: Option<T>) -> Option<&T> {
+        let last = self.last.get_mut(source)?;
+
+        if value.is_some() {
+            *last = value;
+        }
+        last.as_ref()
+    }
+}
+
+impl<T: Clone, const N: usize> Default for SampleHold<T, N> {
+    fn default() -> Self {
+        SampleHold::new()
+    }
+}
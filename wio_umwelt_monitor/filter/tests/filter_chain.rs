@@ -0,0 +1,95 @@
+// ホスト上で実行する
+// cargo test --target x86_64-unknown-linux-gnu
+
+use filter::*;
+
+// 妥当な範囲の外の値は棄却し、前後の値には影響しない
+#[test]
+fn range_reject() {
+    let mut filter = FilterChain::new(FilterConfig { range: Some((250.0, 10000.0)), ..FilterConfig::NONE });
+
+    assert_eq!(filter.update(0, Some(800.0)), Ok(Some(800.0)));
+    assert_eq!(filter.update(12, Some(50.0)), Err(Rejection::OutOfRange));
+    assert_eq!(filter.update(24, Some(20000.0)), Err(Rejection::OutOfRange));
+    assert_eq!(filter.update(36, Some(810.0)), Ok(Some(810.0)));
+    assert_eq!(filter.rejected(), 2);
+}
+
+// 欠測とNaNは棄却せずに欠測のまま返す
+#[test]
+fn missing_passes_through() {
+    let mut filter = FilterChain::new(FilterConfig { range: Some((0.0, 100.0)), ..FilterConfig::NONE });
+
+    assert_eq!(filter.update(0, None), Ok(None));
+    assert_eq!(filter.update(12, Some(f32::NAN)), Ok(None));
+    assert_eq!(filter.rejected(), 0);
+}
+
+// 変化率の上限を超えた値は棄却し、3回続いたらその値を新しい水準として受け付ける
+#[test]
+fn rate_limit_resets_after_consecutive_rejects() {
+    let mut filter = FilterChain::new(FilterConfig { max_rate: Some(1.0), ..FilterConfig::NONE });
+
+    assert_eq!(filter.update(0, Some(1013.0)), Ok(Some(1013.0)));
+    // 12秒で0.2hPaまでの変化は受け付ける
+    assert_eq!(filter.update(12, Some(1013.1)), Ok(Some(1013.1)));
+
+    assert_eq!(filter.update(24, Some(1020.0)), Err(Rejection::TooFast));
+    assert_eq!(filter.update(36, Some(1020.0)), Err(Rejection::TooFast));
+    assert_eq!(filter.update(48, Some(1020.0)), Ok(Some(1020.0)));
+    assert_eq!(filter.rejected(), 2);
+
+    // 新しい水準からの変化率で判定する
+    assert_eq!(filter.update(60, Some(1020.1)), Ok(Some(1020.1)));
+}
+
+// 1回だけの急変は棄却し、続かなければ棄却した回数は数え直す
+#[test]
+fn single_spike_is_rejected() {
+    let mut filter = FilterChain::new(FilterConfig { max_rate: Some(500.0), ..FilterConfig::NONE });
+
+    assert_eq!(filter.update(0, Some(800.0)), Ok(Some(800.0)));
+    assert_eq!(filter.update(12, Some(5000.0)), Err(Rejection::TooFast));
+    assert_eq!(filter.update(24, Some(810.0)), Ok(Some(810.0)));
+    assert_eq!(filter.update(36, Some(5000.0)), Err(Rejection::TooFast));
+    assert_eq!(filter.update(48, Some(5000.0)), Err(Rejection::TooFast));
+    assert_eq!(filter.update(60, Some(820.0)), Ok(Some(820.0)));
+    assert_eq!(filter.rejected(), 3);
+}
+
+// 直近の値の中央値をとる（揃うまでは揃った分の中央値、偶数個なら中央の2つの平均）
+#[test]
+fn median_window() {
+    let mut filter = FilterChain::new(FilterConfig { median: 3, ..FilterConfig::NONE });
+
+    assert_eq!(filter.update(0, Some(10.0)), Ok(Some(10.0)));
+    assert_eq!(filter.update(12, Some(20.0)), Ok(Some(15.0)));
+    assert_eq!(filter.update(24, Some(90.0)), Ok(Some(20.0)));
+    assert_eq!(filter.update(36, Some(30.0)), Ok(Some(30.0)));
+    assert_eq!(filter.update(48, Some(25.0)), Ok(Some(30.0)));
+    assert_eq!(filter.update(60, Some(26.0)), Ok(Some(26.0)));
+}
+
+// 中央値の数はMAX_MEDIANまでに抑える
+#[test]
+fn median_window_is_limited() {
+    let mut filter = FilterChain::new(FilterConfig { median: 100, ..FilterConfig::NONE });
+
+    for i in 0..MAX_MEDIAN as u32 {
+        filter.update(i, Some(0.0)).unwrap();
+    }
+    for i in 0..MAX_MEDIAN as u32 / 2 {
+        assert_eq!(filter.update(100 + i, Some(1.0)), Ok(Some(0.0)));
+    }
+    assert_eq!(filter.update(200, Some(1.0)), Ok(Some(1.0)));
+}
+
+// 指数移動平均は経過時間に応じて新しい値に近づく
+#[test]
+fn smoothing() {
+    let mut filter = FilterChain::new(FilterConfig { smoothing: Some(120.0), ..FilterConfig::NONE });
+
+    assert_eq!(filter.update(0, Some(1000.0)), Ok(Some(1000.0)));
+    // 120秒の時定数に120秒経過したら差の半分だけ近づく
+    assert_eq!(filter.update(120, Some(1010.0)), Ok(Some(1005.0)));
+}
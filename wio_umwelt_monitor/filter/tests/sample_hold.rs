@@ -0,0 +1,37 @@
+// ホスト上で実行する
+// cargo test --target x86_64-unknown-linux-gnu
+
+use filter::*;
+
+// 一方のセンサが測定中でも、もう一方のセンサの新しい値と測定中のセンサの前回の値が揃う
+#[test]
+fn one_source_ready_and_one_not_ready() {
+    let mut hold: SampleHold<(f32, f32), 2> = SampleHold::new();
+
+    assert_eq!(hold.update(0, Some((800.0, 24.0))), Some(&(800.0, 24.0)));
+    assert_eq!(hold.update(1, Some((1013.0, 26.0))), Some(&(1013.0, 26.0)));
+
+    assert_eq!(hold.update(0, None), Some(&(800.0, 24.0)));
+    assert_eq!(hold.update(1, Some((1012.5, 26.5))), Some(&(1012.5, 26.5)));
+
+    assert_eq!(hold.update(0, Some((820.0, 24.5))), Some(&(820.0, 24.5)));
+    assert_eq!(hold.update(1, None), Some(&(1012.5, 26.5)));
+}
+
+// 一度も値を返していないセンサの代わりの値は無い
+#[test]
+fn not_ready_before_first_value() {
+    let mut hold: SampleHold<f32, 2> = SampleHold::new();
+
+    assert_eq!(hold.update(0, None), None);
+    assert_eq!(hold.update(1, Some(1013.0)), Some(&1013.0));
+    assert_eq!(hold.update(0, None), None);
+}
+
+// 登録していない番号のセンサには何も保たない
+#[test]
+fn source_out_of_range() {
+    let mut hold: SampleHold<f32, 2> = SampleHold::new();
+
+    assert_eq!(hold.update(2, Some(1.0)), None);
+}
@@ -0,0 +1,87 @@
+//! host benchmark for DataHistory
+//!
+//! cargo bench --target x86_64-unknown-linux-gnu
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use history::*;
+
+const WINDOW_WIDTH: usize = 320;
+const ITERATIONS: usize = 100_000;
+
+// 以前の実装で欠測を表していた値
+const INVALID_DAT_NUM: f32 = 999.9;
+
+// 以前の実装（配列全体をずらし、毎回最大値・最小値を探し直す）
+struct ShiftHistory {
+    dat: [f32; WINDOW_WIDTH + 1],
+    max: f32,
+    min: f32
+}
+
+impl ShiftHistory {
+    fn new() -> ShiftHistory {
+        ShiftHistory {
+            dat: [INVALID_DAT_NUM; WINDOW_WIDTH + 1],
+            max: 0.0,
+            min: 0.0
+        }
+    }
+
+    fn set_new_data(&mut self, new_data: f32) {
+        self.max = new_data;
+        self.min = new_data;
+        self.dat[WINDOW_WIDTH] = new_data;
+
+        for i in 0..WINDOW_WIDTH {
+            self.dat[i] = self.dat[i + 1];
+            if INVALID_DAT_NUM != self.dat[i] {
+                if self.max < self.dat[i] {
+                    self.max = self.dat[i];
+                }
+                if self.dat[i] < self.min {
+                    self.min = self.dat[i];
+                }
+            }
+        }
+    }
+}
+
+// CO2濃度らしいゆっくりした変動と細かいノイズ
+fn sample(i: usize) -> f32 {
+    let slow = ((i as f32) * 0.01).sin() * 300.0;
+    let noise = ((i * 7919) % 13) as f32;
+    800.0 + slow + noise
+}
+
+fn report(name: &str, elapsed: std::time::Duration) {
+    println!("{:<32} {:>10.1} ns/sample", name, elapsed.as_nanos() as f64 / ITERATIONS as f64);
+}
+
+fn bench_shift() {
+    let mut history = ShiftHistory::new();
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        history.set_new_data(black_box(sample(i)));
+        black_box((history.max, history.min));
+    }
+    report("shift (320)", start.elapsed());
+}
+
+fn bench_ring<const N: usize, const W: usize>(name: &str, window: usize) {
+    let mut history: DataHistory<N, W> = DataHistory::new(window, Encoding::new(0.0, 0.1));
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        history.set_new_data(black_box(Some(sample(i))));
+        black_box((history.max(), history.min()));
+    }
+    report(name, start.elapsed());
+}
+
+fn main() {
+    bench_shift();
+    bench_ring::<320, 320>("ring (320, window 320)", WINDOW_WIDTH);
+    bench_ring::<7200, 320>("ring (7200, window 320)", WINDOW_WIDTH);
+    bench_ring::<7200, 7200>("ring (7200, window 7200)", 7200);
+}
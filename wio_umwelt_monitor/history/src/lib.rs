@@ -0,0 +1,880 @@
+//! measurement history for wio_umwelt_monitor
+
+#![no_std]
+
+
+// 値を符号付き16bitの固定小数点で保持するための符号化（値 = offset + step * 符号）
+// stepを表示の分解能に合わせれば、表示上は元の値と区別できない
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Encoding {
+    pub offset: f32,
+    pub step: f32
+}
+
+// 最大値・最小値の候補の通し番号を古い順に保持する両端キュー
+// 末尾に追加するときに、新しい値より劣る候補を捨てるので常に単調になる
+// 通し番号は追加した回数なので、リングバッファが一周しても古さを取り違えない
+struct MonotonicDeque<const W: usize> {
+    seq: [u32; W],
+    head: usize,
+    len: usize
+}
+
+// 固定長のリングバッファによる履歴（1個2byte、Nは65536以下）
+// 直近window個（W以下）の最大値・最小値を追加のたびに更新する
+pub struct DataHistory<const N: usize, const W: usize> {
+    dat: [i16; N],
+    encoding: Encoding,
+    len: usize,
+    next: usize,
+    // これまでに追加した数（最大値・最小値の候補の通し番号）
+    count: u32,
+    window: usize,
+    max: MonotonicDeque<W>,
+    min: MonotonicDeque<W>
+}
+
+// 直近の変化の傾向
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Trend {
+    // 最新の時点での推定値
+    pub level: f32,
+    // 1個あたりの変化量
+    pub slope: f32
+}
+
+// 欠測を表す符号（値としては使わない）
+const MISSING_CODE: i16 = i16::MIN;
+
+// 傾向を求めるときに使う組の数の上限
+const MAX_TREND_PAIRS: usize = 64;
+
+impl Encoding {
+    pub const fn new(offset: f32, step: f32) -> Encoding {
+        Encoding {
+            offset,
+            step
+        }
+    }
+
+    // 表せる最小値
+    pub fn min_value(&self) -> f32 {
+        self.offset + self.step * (MISSING_CODE + 1) as f32
+    }
+
+    // 表せる最大値
+    pub fn max_value(&self) -> f32 {
+        self.offset + self.step * i16::MAX as f32
+    }
+
+    // 範囲外の値は表せる範囲の端に丸める
+    pub fn encode(&self, value: Option<f32>) -> i16 {
+        match value {
+            Some(value) if !value.is_nan() => {
+                let code = (value - self.offset) / self.step;
+                let code = if code < 0.0 { code - 0.5 } else { code + 0.5 };
+
+                (code as i16).max(MISSING_CODE + 1)
+            },
+            _ => MISSING_CODE
+        }
+    }
+
+    pub fn decode(&self, code: i16) -> Option<f32> {
+        if code == MISSING_CODE {
+            None
+        }
+        else {
+            Some(self.offset + self.step * code as f32)
+        }
+    }
+}
+
+impl<const W: usize> MonotonicDeque<W> {
+    const fn new() -> MonotonicDeque<W> {
+        MonotonicDeque {
+            seq: [0; W],
+            head: 0,
+            len: 0
+        }
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    // 値はリングバッファの、最新の値(通し番号newest_seq、位置newest_pos)から数えた位置から読む
+    // キューには有効な値の通し番号しか入れないので、空でなければ欠測の符号にはならない
+    fn front(&self, dat: &[i16], newest_seq: u32, newest_pos: usize) -> Option<i16> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            Some(dat[locate(self.seq[self.head], newest_seq, newest_pos, dat.len())])
+        }
+    }
+
+    // is_worse(old, new)がtrueの候補を末尾から捨ててから、最新の値を追加する
+    // 符号化は単調なので、符号のまま比較できる
+    fn push(&mut self, dat: &[i16], newest_seq: u32, newest_pos: usize, is_worse: fn(i16, i16) -> bool) {
+        let value = dat[newest_pos];
+
+        while 0 < self.len {
+            let back = (self.head + self.len - 1) % W;
+            if !is_worse(dat[locate(self.seq[back], newest_seq, newest_pos, dat.len())], value) {
+                break;
+            }
+            self.len -= 1;
+        }
+
+        let tail = (self.head + self.len) % W;
+        self.seq[tail] = newest_seq;
+        self.len += 1;
+    }
+
+    // 最新の通し番号newest_seqから数えてwindow個以上前になった先頭の候補を捨てる
+    fn expire(&mut self, newest_seq: u32, window: usize) {
+        while 0 < self.len && window as u32 <= newest_seq.wrapping_sub(self.seq[self.head]) {
+            self.head = (self.head + 1) % W;
+            self.len -= 1;
+        }
+    }
+}
+
+// 通し番号seqの値のリングバッファ上の位置（最新の値から容量capacity未満しか遡らない）
+fn locate(seq: u32, newest_seq: u32, newest_pos: usize, capacity: usize) -> usize {
+    (newest_pos + capacity - newest_seq.wrapping_sub(seq) as usize) % capacity
+}
+
+impl<const N: usize, const W: usize> DataHistory<N, W> {
+    // windowは最大値・最小値を求める範囲（1以上、NとW以下）
+    pub const fn new(window: usize, encoding: Encoding) -> DataHistory<N, W> {
+        DataHistory {
+            dat: [MISSING_CODE; N],
+            encoding,
+            len: 0,
+            next: 0,
+            count: 0,
+            window: Self::limit_window(window),
+            max: MonotonicDeque::new(),
+            min: MonotonicDeque::new()
+        }
+    }
+
+    // 履歴を消して最大値・最小値を求める範囲と符号化を設定し直す
+    pub fn reset(&mut self, window: usize, encoding: Encoding) {
+        self.encoding = encoding;
+        self.window = Self::limit_window(window);
+        self.len = 0;
+        self.next = 0;
+        self.count = 0;
+        self.max.clear();
+        self.min.clear();
+    }
+
+    const fn limit_window(window: usize) -> usize {
+        let limit = if N < W { N } else { W };
+
+        if limit < window { limit } else if window == 0 { 1 } else { window }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // 欠測のときはNoneを追加する（時間軸を保つため）
+    pub fn set_new_data(&mut self, new_data: Option<f32>) {
+        let pos = self.next;
+        let seq = self.count;
+
+        self.dat[pos] = self.encoding.encode(new_data);
+        self.len = (self.len + 1).min(N);
+        self.next = (pos + 1) % N;
+        self.count = seq.wrapping_add(1);
+
+        // 範囲外になる候補を先に捨てておけば、キューの長さはwindowを超えない
+        self.max.expire(seq, self.window);
+        self.min.expire(seq, self.window);
+
+        if self.dat[pos] != MISSING_CODE {
+            self.max.push(&self.dat, seq, pos, |old, new| old <= new);
+            self.min.push(&self.dat, seq, pos, |old, new| new <= old);
+        }
+    }
+
+    // 直近window個の中での最大値（有効な値が無ければNone）
+    pub fn max(&self) -> Option<f32> {
+        let (seq, pos) = self.newest();
+        self.max.front(&self.dat, seq, pos).and_then(|code| self.encoding.decode(code))
+    }
+
+    // 直近window個の中での最小値（有効な値が無ければNone）
+    pub fn min(&self) -> Option<f32> {
+        let (seq, pos) = self.newest();
+        self.min.front(&self.dat, seq, pos).and_then(|code| self.encoding.decode(code))
+    }
+
+    // 最新の値の通し番号とリングバッファ上の位置
+    fn newest(&self) -> (u32, usize) {
+        (self.count.wrapping_sub(1), (self.next + N - 1) % N)
+    }
+
+    // 直近window個の中での最小値と最大値（有効な値が無ければNone）
+    pub fn range(&self) -> Option<(f32, f32)> {
+        match (self.min(), self.max()) {
+            (Some(min), Some(max)) => Some((min, max)),
+            _ => None
+        }
+    }
+
+    // 直近window個を古い順に並べたときのitr番目の値
+    pub fn get_value(&self, itr: usize) -> Option<f32> {
+        if self.window <= itr {
+            return None;
+        }
+        self.get_past(self.window - 1 - itr)
+    }
+
+    // age個前の値（0が最新）
+    pub fn get_past(&self, age: usize) -> Option<f32> {
+        if self.len <= age {
+            None
+        }
+        else {
+            self.encoding.decode(self.dat[(self.next + N - 1 - age) % N])
+        }
+    }
+
+    pub fn get_latest(&self) -> Option<f32> {
+        self.get_past(0)
+    }
+
+    // 最小値を0、最大値を1としたときのitr番目の値（欠測はNone）
+    // 値がすべて同じときは0とする
+    pub fn get_rate(&self, itr: usize) -> Option<f32> {
+        let value = self.get_value(itr)?;
+        let (min, max) = self.range()?;
+
+        if 0.0 < max - min {
+            Some((value - min) / (max - min))
+        }
+        else {
+            Some(0.0)
+        }
+    }
+
+    // age個前からさらに古いcount個の値の平均
+    // 有効な値が半分に満たなければNone
+    pub fn mean(&self, age: usize, count: usize) -> Option<f32> {
+        let mut sum = 0.0;
+        let mut valid = 0;
+        for age in age..(age + count).min(self.len) {
+            if let Some(value) = self.get_past(age) {
+                sum += value;
+                valid += 1;
+            }
+        }
+
+        if valid == 0 || valid * 2 < count {
+            None
+        }
+        else {
+            Some(sum / valid as f32)
+        }
+    }
+
+    // 直近count個（128個まで）から傾向を求める
+    // 半分離れた値の組ごとの傾きの中央値（Theil-Sen推定の簡略版）を使うので、
+    // ノイズや短いスパイクの影響を受けにくい
+    // 有効な組が半分に満たなければNone
+    pub fn trend(&self, count: usize) -> Option<Trend> {
+        let count = count.min(self.len).min(2 * MAX_TREND_PAIRS);
+        let half = count / 2;
+        if half < 2 {
+            return None;
+        }
+
+        let mut slopes = [0.0; MAX_TREND_PAIRS];
+        let mut pairs = 0;
+        for age in 0..half {
+            if let (Some(newer), Some(older)) = (self.get_past(age), self.get_past(age + half)) {
+                slopes[pairs] = (newer - older) / half as f32;
+                pairs += 1;
+            }
+        }
+        if pairs * 2 < half {
+            return None;
+        }
+        let slope = median(&mut slopes[..pairs]);
+
+        // 各値を最新の時点に外挿したものの中央値を推定値とする
+        let mut levels = [0.0; 2 * MAX_TREND_PAIRS];
+        let mut valid = 0;
+        for age in 0..count {
+            if let Some(value) = self.get_past(age) {
+                levels[valid] = value + slope * age as f32;
+                valid += 1;
+            }
+        }
+        let level = median(&mut levels[..valid]);
+
+        Some(Trend {
+            level,
+            slope
+        })
+    }
+}
+
+// 並べ替えて中央値を求める（空でないこと）
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+
+    let middle = values.len() / 2;
+    if values.len() % 2 == 1 {
+        values[middle]
+    }
+    else {
+        (values[middle - 1] + values[middle]) / 2.0
+    }
+}
+
+// 一定期間の集計値
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bucket {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub count: u16
+}
+
+// 符号化して保持する集計値（1個8byte）
+#[derive(Debug, Copy, Clone)]
+struct PackedBucket {
+    min: i16,
+    max: i16,
+    mean: i16,
+    count: u16
+}
+
+// 一定期間ごとに集計した値を保持するリングバッファ
+// 集計中のバケットだけは符号化せずに持つ
+pub struct AggregateTier<const N: usize> {
+    period: u32,
+    encoding: Encoding,
+    buckets: [PackedBucket; N],
+    len: usize,
+    next: usize,
+    current: Bucket,
+    current_index: u32,
+    started: bool
+}
+
+// 集計の粒度
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Resolution {
+    Raw,
+    Minute,
+    TenMinutes,
+    Hour
+}
+
+// 各段の1バケットの秒数
+const MINUTE_PERIOD: u32 = 60;
+const TEN_MINUTES_PERIOD: u32 = 600;
+const HOUR_PERIOD: u32 = 3600;
+
+pub const MINUTE_CAPACITY: usize = 240;
+pub const TEN_MINUTES_CAPACITY: usize = 144;
+pub const HOUR_CAPACITY: usize = 168;
+
+// 測定値そのものと、1分・10分・1時間ごとの集計を段階的に保持する履歴
+pub struct TieredHistory<const N: usize, const W: usize> {
+    raw: DataHistory<N, W>,
+    interval: u32,
+    minute: AggregateTier<MINUTE_CAPACITY>,
+    ten_minutes: AggregateTier<TEN_MINUTES_CAPACITY>,
+    hour: AggregateTier<HOUR_CAPACITY>
+}
+
+// 指定した期間を指定した幅で描くための問い合わせ結果
+pub struct Query<'a, const N: usize, const W: usize> {
+    history: &'a TieredHistory<N, W>,
+    pub resolution: Resolution,
+    span: u32,
+    width: usize
+}
+
+impl Bucket {
+    pub const EMPTY: Bucket = Bucket {
+        min: 0.0,
+        max: 0.0,
+        mean: 0.0,
+        count: 0
+    };
+
+    pub fn from_value(value: Option<f32>) -> Bucket {
+        match value {
+            Some(value) => Bucket {
+                min: value,
+                max: value,
+                mean: value,
+                count: 1
+            },
+            None => Bucket::EMPTY
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn merge(&mut self, other: &Bucket) {
+        if other.is_empty() {
+            return;
+        }
+
+        if self.is_empty() {
+            *self = *other;
+            return;
+        }
+
+        let count = self.count as f32 + other.count as f32;
+        self.mean = (self.mean * self.count as f32 + other.mean * other.count as f32) / count;
+        self.min = if other.min < self.min { other.min } else { self.min };
+        self.max = if self.max < other.max { other.max } else { self.max };
+        self.count = self.count.saturating_add(other.count);
+    }
+}
+
+impl PackedBucket {
+    const EMPTY: PackedBucket = PackedBucket {
+        min: MISSING_CODE,
+        max: MISSING_CODE,
+        mean: MISSING_CODE,
+        count: 0
+    };
+
+    fn pack(bucket: &Bucket, encoding: &Encoding) -> PackedBucket {
+        if bucket.is_empty() {
+            PackedBucket::EMPTY
+        }
+        else {
+            PackedBucket {
+                min: encoding.encode(Some(bucket.min)),
+                max: encoding.encode(Some(bucket.max)),
+                mean: encoding.encode(Some(bucket.mean)),
+                count: bucket.count
+            }
+        }
+    }
+
+    fn unpack(&self, encoding: &Encoding) -> Bucket {
+        match (encoding.decode(self.min), encoding.decode(self.max), encoding.decode(self.mean)) {
+            (Some(min), Some(max), Some(mean)) if 0 < self.count => Bucket {
+                min,
+                max,
+                mean,
+                count: self.count
+            },
+            _ => Bucket::EMPTY
+        }
+    }
+}
+
+impl<const N: usize> AggregateTier<N> {
+    // periodは1バケットの秒数
+    pub const fn new(period: u32, encoding: Encoding) -> AggregateTier<N> {
+        AggregateTier {
+            period,
+            encoding,
+            buckets: [PackedBucket::EMPTY; N],
+            len: 0,
+            next: 0,
+            current: Bucket::EMPTY,
+            current_index: 0,
+            started: false
+        }
+    }
+
+    // 集計を消して1バケットの秒数と符号化を設定し直す
+    pub fn reset(&mut self, period: u32, encoding: Encoding) {
+        self.period = period;
+        self.encoding = encoding;
+        self.len = 0;
+        self.next = 0;
+        self.current = Bucket::EMPTY;
+        self.current_index = 0;
+        self.started = false;
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    // timestampが属するバケットまで進める
+    // 集計中のバケットが確定したら、その開始時刻と集計値を返す
+    pub fn advance(&mut self, timestamp: u32) -> Option<(u32, Bucket)> {
+        let index = timestamp / self.period;
+
+        if !self.started {
+            self.started = true;
+            self.current_index = index;
+            return None;
+        }
+
+        if index <= self.current_index {
+            return None;
+        }
+
+        let closed = self.current;
+        let closed_start = self.current_index * self.period;
+        self.push(PackedBucket::pack(&closed, &self.encoding));
+
+        // 何も無かった期間は空のバケットで埋める
+        let skipped = ((index - self.current_index - 1) as usize).min(N);
+        for _ in 0..skipped {
+            self.push(PackedBucket::EMPTY);
+        }
+
+        self.current = Bucket::EMPTY;
+        self.current_index = index;
+
+        if closed.is_empty() {
+            None
+        }
+        else {
+            Some((closed_start, closed))
+        }
+    }
+
+    // 集計中のバケットに加える
+    pub fn merge(&mut self, bucket: &Bucket) {
+        self.current.merge(bucket);
+    }
+
+    // age個前のバケット（0が集計中のバケット）
+    pub fn get(&self, age: usize) -> Bucket {
+        if age == 0 {
+            self.current
+        }
+        else if self.len < age {
+            Bucket::EMPTY
+        }
+        else {
+            self.buckets[(self.next + N - age) % N].unpack(&self.encoding)
+        }
+    }
+
+    fn push(&mut self, bucket: PackedBucket) {
+        self.buckets[self.next] = bucket;
+        self.len = (self.len + 1).min(N);
+        self.next = (self.next + 1) % N;
+    }
+}
+
+impl<const N: usize, const W: usize> TieredHistory<N, W> {
+    // windowは測定値の最大値・最小値を求める範囲、intervalは測定間隔[s]
+    pub const fn new(window: usize, interval: u32, encoding: Encoding) -> TieredHistory<N, W> {
+        TieredHistory {
+            raw: DataHistory::new(window, encoding),
+            interval,
+            minute: AggregateTier::new(MINUTE_PERIOD, encoding),
+            ten_minutes: AggregateTier::new(TEN_MINUTES_PERIOD, encoding),
+            hour: AggregateTier::new(HOUR_PERIOD, encoding)
+        }
+    }
+
+    // 履歴を消して全ての設定をし直す（newと同じ状態になる）
+    // 大きな構造体を作り直さずに済むように、その場で初期化する
+    // ゼロで埋めた領域に置いたものも、これを呼べば使える
+    pub fn reset(&mut self, window: usize, interval: u32, encoding: Encoding) {
+        self.interval = interval;
+        self.raw.reset(window, encoding);
+        self.minute.reset(MINUTE_PERIOD, encoding);
+        self.ten_minutes.reset(TEN_MINUTES_PERIOD, encoding);
+        self.hour.reset(HOUR_PERIOD, encoding);
+    }
+
+    pub fn raw(&self) -> &DataHistory<N, W> {
+        &self.raw
+    }
+
+    // 測定値を追加し、各段の集計に反映する（欠測はNone）
+    pub fn set_new_data(&mut self, timestamp: u32, new_data: Option<f32>) {
+        self.raw.set_new_data(new_data);
+
+        let closed = self.minute.advance(timestamp);
+        self.minute.merge(&Bucket::from_value(new_data));
+
+        // 確定したバケットを次の段に渡していく
+        if let Some((start, bucket)) = closed {
+            let closed = self.ten_minutes.advance(start);
+            self.ten_minutes.merge(&bucket);
+
+            if let Some((start, bucket)) = closed {
+                self.hour.advance(start);
+                self.hour.merge(&bucket);
+            }
+        }
+    }
+
+    pub fn period(&self, resolution: Resolution) -> u32 {
+        match resolution {
+            Resolution::Raw => self.interval,
+            Resolution::Minute => self.minute.period(),
+            Resolution::TenMinutes => self.ten_minutes.period(),
+            Resolution::Hour => self.hour.period()
+        }
+    }
+
+    pub fn capacity(&self, resolution: Resolution) -> usize {
+        match resolution {
+            Resolution::Raw => self.raw.capacity(),
+            Resolution::Minute => self.minute.capacity(),
+            Resolution::TenMinutes => self.ten_minutes.capacity(),
+            Resolution::Hour => self.hour.capacity()
+        }
+    }
+
+    // age個前のバケット（0が最新）
+    pub fn get(&self, resolution: Resolution, age: usize) -> Bucket {
+        match resolution {
+            Resolution::Raw => Bucket::from_value(self.raw.get_past(age)),
+            Resolution::Minute => self.minute.get(age),
+            Resolution::TenMinutes => self.ten_minutes.get(age),
+            Resolution::Hour => self.hour.get(age)
+        }
+    }
+
+    // 段resolutionが期間span[s]を保持しているかどうか
+    fn is_covered(&self, resolution: Resolution, span: u32) -> bool {
+        span as u64 <= self.period(resolution) as u64 * self.capacity(resolution) as u64
+    }
+
+    // いずれかの段が期間span[s]を保持しているかどうか
+    pub fn covers(&self, span: u32) -> bool {
+        [Resolution::Raw, Resolution::Minute, Resolution::TenMinutes, Resolution::Hour].iter()
+            .any(|resolution| self.is_covered(*resolution, span))
+    }
+
+    // 期間spanを保持している段のうち、1画素に1バケット以上ある最も粗い段を選ぶ
+    // そのような段が無ければ、期間spanを保持している最も細かい段を選ぶ
+    pub fn select(&self, span: u32, width: usize) -> Resolution {
+        let resolutions = [Resolution::Raw, Resolution::Minute, Resolution::TenMinutes, Resolution::Hour];
+        let per_pixel = span / width.max(1) as u32;
+        let mut selected = None;
+
+        for resolution in resolutions.iter() {
+            let period = self.period(*resolution);
+
+            if !self.is_covered(*resolution, span) {
+                continue;
+            }
+
+            if selected.is_none() || period <= per_pixel {
+                selected = Some(*resolution);
+            }
+        }
+
+        selected.unwrap_or(Resolution::Hour)
+    }
+
+    pub fn query(&self, span: u32, width: usize) -> Query<'_, N, W> {
+        Query {
+            history: self,
+            resolution: self.select(span, width),
+            span,
+            width: width.max(1)
+        }
+    }
+}
+
+impl<'a, const N: usize, const W: usize> Query<'a, N, W> {
+    // 左端を0とするcolumn列目に描く集計値
+    pub fn get(&self, column: usize) -> Bucket {
+        if self.width <= column {
+            return Bucket::EMPTY;
+        }
+
+        let period = self.history.period(self.resolution) as u64;
+        let span = self.span as u64;
+        let width = self.width as u64;
+
+        // 右端が最新になるように、列が受け持つ期間のバケットをまとめる
+        let from = (width - 1 - column as u64) * span / width / period;
+        let to = ((width - column as u64) * span / width / period).max(from + 1);
+
+        let mut bucket = Bucket::EMPTY;
+        for age in from..to {
+            bucket.merge(&self.history.get(self.resolution, age as usize));
+        }
+        bucket
+    }
+
+    // 描く範囲の最小値と最大値
+    pub fn range(&self) -> Option<(f32, f32)> {
+        let raw = &self.history.raw;
+        if self.resolution == Resolution::Raw && self.span == self.history.interval * raw.window() as u32 {
+            // 測定値の表示幅ちょうどなら逐次更新している値を使う
+            return raw.range();
+        }
+
+        let mut range = Bucket::EMPTY;
+        for column in 0..self.width {
+            range.merge(&self.get(column));
+        }
+
+        if range.is_empty() {
+            None
+        }
+        else {
+            Some((range.min, range.max))
+        }
+    }
+}
+
+// 1日分の集計（時刻は0時からの分）
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DailySummary {
+    pub day: u32,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub min_time: u16,
+    pub max_time: u16,
+    pub count: u16
+}
+
+// 日ごとの集計を締めた日からD日分保持する（集計中の日は別に持つ）
+pub struct DailyHistory<const D: usize> {
+    days: [DailySummary; D],
+    len: usize,
+    next: usize,
+    current: Option<DailySummary>,
+    // 集計中の日の合計（1日分を足してもf32では丸めで平均がずれるのでf64で持つ）
+    sum: f64
+}
+
+impl DailySummary {
+    const EMPTY: DailySummary = DailySummary {
+        day: 0,
+        min: 0.0,
+        max: 0.0,
+        mean: 0.0,
+        min_time: 0,
+        max_time: 0,
+        count: 0
+    };
+}
+
+impl<const D: usize> DailyHistory<D> {
+    pub const fn new() -> DailyHistory<D> {
+        DailyHistory {
+            days: [DailySummary::EMPTY; D],
+            len: 0,
+            next: 0,
+            current: None,
+            sum: 0.0
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.next = 0;
+        self.current = None;
+        self.sum = 0.0;
+    }
+
+    // 集計中の日も含めた日数
+    pub fn len(&self) -> usize {
+        self.len + self.current.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // dayは日付の通し番号、time_of_dayは0時からの秒数
+    // 日付が変わったら集計中の日を締める（欠測は数えない）
+    pub fn set_new_data(&mut self, day: u32, time_of_day: u32, new_data: Option<f32>) {
+        if let Some(current) = self.current {
+            if current.day != day {
+                self.days[self.next] = current;
+                self.next = (self.next + 1) % D;
+                self.len = (self.len + 1).min(D);
+                self.current = None;
+            }
+        }
+
+        let value = match new_data {
+            Some(value) => value,
+            None => return
+        };
+        let minutes = (time_of_day / 60) as u16;
+
+        match self.current.as_mut() {
+            Some(current) => {
+                if value < current.min {
+                    current.min = value;
+                    current.min_time = minutes;
+                }
+                if current.max < value {
+                    current.max = value;
+                    current.max_time = minutes;
+                }
+                current.count = current.count.saturating_add(1);
+                self.sum += value as f64;
+                current.mean = (self.sum / current.count as f64) as f32;
+            },
+            None => {
+                self.sum = value as f64;
+                self.current = Some(DailySummary {
+                    day,
+                    min: value,
+                    max: value,
+                    mean: value,
+                    min_time: minutes,
+                    max_time: minutes,
+                    count: 1
+                });
+            }
+        }
+    }
+
+    // 日付dayの集計（集計中の日も含む、値が1つも無かった日や古すぎる日はNone）
+    pub fn find(&self, day: u32) -> Option<DailySummary> {
+        match self.current {
+            Some(current) if current.day == day => Some(current),
+            _ => (0..self.len)
+                .map(|age| self.days[(self.next + D - 1 - age) % D])
+                .find(|summary| summary.day == day)
+        }
+    }
+}
+
+impl<const D: usize> Default for DailyHistory<D> {
+    fn default() -> DailyHistory<D> {
+        DailyHistory::new()
+    }
+}
@@ -0,0 +1,35 @@
+// ホスト上で実行する
+// cargo test --target x86_64-unknown-linux-gnu
+
+use history::*;
+
+// 12秒ごとに1日分（7200個）足しても、一定の値の平均はその値のまま
+#[test]
+fn constant_mean_over_a_day() {
+    let mut daily: DailyHistory<2> = DailyHistory::new();
+    let pressure = 1013.2;
+
+    for i in 0..7200 {
+        daily.set_new_data(0, i * 12, Some(pressure));
+    }
+
+    let summary = daily.find(0).unwrap();
+    assert_eq!(summary.count, 7200);
+    assert_eq!(summary.mean, pressure);
+    assert_eq!(summary.min, pressure);
+    assert_eq!(summary.max, pressure);
+}
+
+// 日付が変わったら締めて、翌日は新しく集計する
+#[test]
+fn closes_the_day() {
+    let mut daily: DailyHistory<2> = DailyHistory::new();
+
+    daily.set_new_data(0, 0, Some(1.0));
+    daily.set_new_data(0, 60, Some(3.0));
+    daily.set_new_data(1, 0, Some(10.0));
+
+    assert_eq!(daily.find(0).map(|summary| summary.mean), Some(2.0));
+    assert_eq!(daily.find(1).map(|summary| summary.mean), Some(10.0));
+    assert_eq!(daily.len(), 2);
+}
@@ -0,0 +1,57 @@
+// ホスト上で実行する
+// cargo test --target x86_64-unknown-linux-gnu
+
+use history::*;
+
+// 最大値・最小値の範囲がリングバッファの容量と同じとき、一周した後も古い値を残さない
+#[test]
+fn window_equal_to_capacity() {
+    let mut history: DataHistory<4, 4> = DataHistory::new(4, Encoding::new(0.0, 1.0));
+
+    for value in [10.0, 60.0, 50.0, 90.0].iter() {
+        history.set_new_data(Some(*value));
+    }
+    assert_eq!(history.max(), Some(90.0));
+    assert_eq!(history.min(), Some(10.0));
+
+    history.set_new_data(Some(20.0));
+    assert_eq!(history.max(), Some(90.0));
+    assert_eq!(history.min(), Some(20.0));
+
+    for _ in 0..4 {
+        history.set_new_data(Some(30.0));
+    }
+    assert_eq!(history.max(), Some(30.0));
+    assert_eq!(history.min(), Some(30.0));
+}
+
+// 範囲を過ぎた最大値は何周しても残らない
+#[test]
+fn window_equal_to_capacity_over_many_laps() {
+    let mut history: DataHistory<4, 4> = DataHistory::new(4, Encoding::new(0.0, 1.0));
+
+    for i in 0..100 {
+        let value = (i % 7) as f32;
+        history.set_new_data(Some(value));
+
+        let expected = (0..4.min(i + 1)).map(|age| ((i - age) % 7) as f32);
+        assert_eq!(history.max(), expected.clone().reduce(f32::max));
+        assert_eq!(history.min(), expected.reduce(f32::min));
+    }
+}
+
+// 派生チャネルの履歴と同じ、グラフの幅320個を容量と範囲の両方にした場合
+#[test]
+fn window_equal_to_capacity_at_graph_width() {
+    let mut history: DataHistory<320, 320> = DataHistory::new(320, Encoding::new(0.0, 0.1));
+
+    history.set_new_data(Some(50.0));
+    for _ in 0..319 {
+        history.set_new_data(Some(20.0));
+    }
+    assert_eq!(history.max(), Some(50.0));
+
+    history.set_new_data(Some(20.0));
+    assert_eq!(history.max(), Some(20.0));
+    assert_eq!(history.min(), Some(20.0));
+}
@@ -0,0 +1,67 @@
+// ホスト上で実行する
+// cargo test --target x86_64-unknown-linux-gnu
+
+use core::mem::MaybeUninit;
+use history::*;
+
+// ゼロで埋めた領域に置いたものも、resetすればnewで作ったものと同じに使える
+#[test]
+fn reset_from_zeroed() {
+    let encoding = Encoding::new(0.0, 0.1);
+    let mut zeroed: TieredHistory<8, 4> = unsafe { MaybeUninit::zeroed().assume_init() };
+    zeroed.reset(4, 12, encoding);
+    let mut expected: TieredHistory<8, 4> = TieredHistory::new(4, 12, encoding);
+
+    let resolutions = [Resolution::Raw, Resolution::Minute, Resolution::TenMinutes, Resolution::Hour];
+    for resolution in resolutions.iter() {
+        assert_eq!(zeroed.period(*resolution), expected.period(*resolution));
+    }
+
+    for i in 0..1000 {
+        let timestamp = i * 12;
+        let value = Some((i % 50) as f32);
+        zeroed.set_new_data(timestamp, value);
+        expected.set_new_data(timestamp, value);
+    }
+
+    assert_eq!(zeroed.raw().window(), 4);
+    assert_eq!(zeroed.raw().range(), expected.raw().range());
+    for resolution in resolutions.iter() {
+        for age in 0..8 {
+            assert_eq!(zeroed.get(*resolution, age), expected.get(*resolution, age));
+        }
+    }
+}
+
+// 表示幅の分しか測定値を持たない履歴（派生チャネルの大きさ）でも、集計の段で7日間を描ける
+// 測定値を24時間分持つ履歴と比べて、各列は集計の粒度の分しか違わない
+#[test]
+fn short_raw_history_covers_long_spans() {
+    let encoding = Encoding::new(0.0, 0.1);
+    let mut short: TieredHistory<320, 320> = TieredHistory::new(320, 12, encoding);
+    let mut full: TieredHistory<7200, 320> = TieredHistory::new(320, 12, encoding);
+
+    // 5分ごとに1ずつ上下する値を8日分
+    for i in 0..8 * 24 * 300 {
+        let step = (i / 25) % 200;
+        let value = Some(if step < 100 { step } else { 200 - step } as f32);
+        short.set_new_data(i * 12, value);
+        full.set_new_data(i * 12, value);
+    }
+
+    for span in [320 * 12, 4 * 3600, 24 * 3600, 7 * 24 * 3600].iter() {
+        assert!(short.covers(*span));
+
+        let short = short.query(*span, 320);
+        let full = full.query(*span, 320);
+        assert_eq!(short.range(), full.range());
+
+        for column in 0..320 {
+            let (short, full) = (short.get(column), full.get(column));
+            assert!(!short.is_empty());
+            assert!((short.mean - full.mean).abs() < 2.0);
+        }
+    }
+
+    assert!(!short.covers(8 * 24 * 3600));
+}
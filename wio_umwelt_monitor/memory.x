@@ -0,0 +1,11 @@
+MEMORY
+{
+  /* Leave 16k for the default bootloader on the Wio Terminal */
+  /* and the last 8k erase block for the calibration stored by src/nvm.rs */
+  FLASH (rx)  : ORIGIN = 0x00000000 + 16K, LENGTH = 512K - 16K - 8K
+  RAM   (rxw) : ORIGIN = 0x20000000, LENGTH = 192K
+}
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
+
+/* src/nvm.rs uses the erase block right after the program area */
+_nvm_block_start = ORIGIN(FLASH) + LENGTH(FLASH);
@@ -4,49 +4,55 @@
 
 use panic_halt as _;
 use wio_terminal as wio;
-use wio_terminal::prelude::*;
-use wio::hal::gpio::*;
-use wio::hal::sercom::*;
+use wio::hal::hal::blocking::i2c::{Read, Write};
+use units::*;
 
 
-pub struct SCD30 {
+pub const SCD30_ADDRESS: u8 = 0x61;
+
+pub struct SCD30<I2C> {
+    i2c: I2C,
     scd30_address: u8
 }
 
-impl SCD30 {
-    pub fn new() -> SCD30 {
+impl<I2C> SCD30<I2C>
+where
+    I2C: Read + Write
+{
+    pub fn new(i2c: I2C) -> SCD30<I2C> {
         SCD30 {
-            scd30_address: 0x61
+            i2c,
+            scd30_address: SCD30_ADDRESS
         }
     }
 
-    pub fn init(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, interval: u16) -> Result<(), ()> {
+    pub fn init(&mut self, interval: u16) -> Result<(), ()> {
 
         // 2 seconds between measurements
-        self.set_measurement_interval(i2c, interval)?;
+        self.set_measurement_interval(interval)?;
 
         // start periodic measuments
-        self.start_periodic_measurment(i2c)
+        self.start_periodic_measurment()
     }
 
-    pub fn set_measurement_interval(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, interval: u16) -> Result<(), ()> {
+    pub fn set_measurement_interval(&mut self, interval: u16) -> Result<(), ()> {
         let scd30_set_measurement_interval: u16 = 0x4600;
-        self.write_command(i2c, scd30_set_measurement_interval, interval)
+        self.write_command(scd30_set_measurement_interval, interval)
     }
 
-    pub fn start_periodic_measurment(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>) -> Result<(), ()> {
+    pub fn start_periodic_measurment(&mut self) -> Result<(), ()> {
         let scd30_continuous_measurement: u16 = 0x0010;
-        self.write_command(i2c, scd30_continuous_measurement, 0x0000)
+        self.write_command(scd30_continuous_measurement, 0x0000)
     }
 
-    pub fn is_available(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>) -> Result<bool, ()> {
+    pub fn is_available(&mut self) -> Result<bool, ()> {
         let mut data: [u8; 2] = [0, 0];
 
-        if let Err(_) = i2c.write(self.scd30_address, &[0x02, 0x02]) {
+        if let Err(_) = self.i2c.write(self.scd30_address, &[0x02, 0x02]) {
             return Err(());
         }
 
-        if let Err(_) = i2c.read(self.scd30_address, &mut data) {
+        if let Err(_) = self.i2c.read(self.scd30_address, &mut data) {
             return Err(());
         }
 
@@ -58,23 +64,44 @@ impl SCD30 {
         }
     }
 
-    pub fn set_auto_calibration(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, enable: bool) -> Result<(), ()> {
+    pub fn read_firmware_version(&mut self) -> Result<u16, ()> {
+        let mut data: [u8; 3] = [0, 0, 0];
+
+        if self.i2c.write(self.scd30_address, &[0xD1, 0x00]).is_err() {
+            return Err(());
+        }
+
+        if self.i2c.read(self.scd30_address, &mut data).is_err() {
+            return Err(());
+        }
+
+        let version = ((data[0] as u16) << 8) | (data[1] as u16);
+
+        // CRCが一致しなければSCD30ではないとみなす
+        if self.calculate_crc(version) != data[2] {
+            return Err(());
+        }
+
+        Ok(version)
+    }
+
+    pub fn set_auto_calibration(&mut self, enable: bool) -> Result<(), ()> {
         if enable {
-            self.write_command(i2c, 0x5306, 1)
+            self.write_command(0x5306, 1)
         }
         else {
-            self.write_command(i2c, 0x5306, 0)
+            self.write_command(0x5306, 0)
         }
     }
 
-    pub fn get_value(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>) -> Result<(f32, f32, f32), ()> {
+    pub fn get_value(&mut self) -> Result<(Ppm, Celsius, RelativeHumidity), ()> {
         let mut buf: [u8; 18] = [0; 18];
 
-        if let Err(_) = i2c.write(self.scd30_address, &[0x03, 0x00]) {
+        if let Err(_) = self.i2c.write(self.scd30_address, &[0x03, 0x00]) {
             return Err(());
         }
 
-        if let Err(_) = i2c.read(self.scd30_address, &mut buf) {
+        if let Err(_) = self.i2c.read(self.scd30_address, &mut buf) {
             return Err(());
         }
 
@@ -88,7 +115,7 @@ impl SCD30 {
         let tmp: u32 =(data[4] << 24) | (data[5] << 16) | (data[6]  << 8) | data[7];
         let hum : u32 =(data[8] << 24) | (data[9] << 16) | (data[10] << 8) | data[11];
 
-        Ok((self.convert_bin2float(co2), self.convert_bin2float(tmp), self.convert_bin2float(hum)))
+        Ok((Ppm(self.convert_bin2float(co2)), Celsius(self.convert_bin2float(tmp)), RelativeHumidity(self.convert_bin2float(hum))))
     }
 
     fn convert_bin2float(&mut self, data: u32) -> f32 {
@@ -113,19 +140,19 @@ impl SCD30 {
         converted
     }
 
-    pub fn stop_measurement(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>) -> Result<(), ()> {
-        match i2c.write(self.scd30_address, &[0x01, 0x04]) {
+    pub fn stop_measurement(&mut self) -> Result<(), ()> {
+        match self.i2c.write(self.scd30_address, &[0x01, 0x04]) {
             Ok(_) => Ok(()),
             _ => Err(())
         }
     }
 
-    fn write_command(&mut self, i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>, command: u16, arguments: u16) -> Result<(), ()> {
+    fn write_command(&mut self, command: u16, arguments: u16) -> Result<(), ()> {
 
         let crc = self.calculate_crc(arguments);
         let buf :[u8; 5] = [(command >> 8) as u8, (command & 0x00ff) as u8, (arguments >> 8) as u8, (arguments & 0x00ff) as u8, crc];
 
-        match i2c.write(self.scd30_address, &buf) {
+        match self.i2c.write(self.scd30_address, &buf) {
             Ok(_) => Ok(()),
             _ => Err(())
         }
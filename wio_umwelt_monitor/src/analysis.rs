@@ -0,0 +1,106 @@
+//! analysis of measurements for wio_umwelt_monitor
+
+use crate::clock::Clock;
+use crate::events::*;
+use crate::exposure::*;
+use crate::infection::*;
+use crate::logger::Logger;
+use crate::mold::*;
+use crate::occupancy::*;
+use crate::sensor::*;
+use crate::ventilation::*;
+
+
+// 測定値を積み重ねて求める解析結果
+pub struct Analysis {
+    pub ventilation: AchEstimator,
+    pub occupancy: Option<OccupancyEstimator>,
+    pub infection: InfectionModel,
+    pub mold: MoldTracker,
+    mold_risk: MoldRisk,
+    pub exposure: ExposureTracker,
+    pub events: EventDetector
+}
+
+impl Analysis {
+    // outdoor_co2は外気のCO2濃度[ppm]、intervalは測定間隔[s]
+    // roomが無ければ在室人数は推定しない
+    pub fn new(outdoor_co2: f32, room: Option<RoomSettings>, activity: Activity, mold: MoldSettings,
+        exposure: ExposureSettings, clock: Clock, interval: u32) -> Analysis {
+        Analysis {
+            ventilation: AchEstimator::new(outdoor_co2),
+            occupancy: room.map(|room| OccupancyEstimator::new(room, interval)),
+            infection: InfectionModel::new(outdoor_co2, activity),
+            mold: MoldTracker::new(mold),
+            mold_risk: MoldRisk::None,
+            exposure: ExposureTracker::new(exposure, clock),
+            events: EventDetector::new(interval)
+        }
+    }
+
+    // 解析から求めて測定結果に加えるチャネル
+    pub fn kinds(&self) -> &'static [SensorType] {
+        if self.occupancy.is_some() {
+            &[SensorType::Occupancy]
+        }
+        else {
+            &[]
+        }
+    }
+
+    // 1回分の測定結果で各解析を進め、求まった結果を記録する
+    // 推定した値はチャネルとして測定結果に加える
+    pub fn update(&mut self, measurement: &mut Measurement, logger: &mut Logger) {
+        let timestamp = measurement.timestamp;
+        let co2 = measurement.get(SensorType::Co2Concentration);
+
+        if let Some(occupancy) = self.occupancy.as_mut() {
+            // 確からしさが低い換気回数は使わない
+            let ach = self.ventilation.latest()
+                .filter(|estimate| Confidence::Medium <= estimate.confidence)
+                .map(|estimate| estimate.ach);
+
+            let people = occupancy.update(co2, self.ventilation.baseline(), ach);
+            measurement.set_estimate(SensorType::Occupancy, people);
+        }
+
+        // カビのリスクの段階が変わったときだけ記録する
+        let mold_risk = self.mold.update(timestamp,
+            measurement.get(SensorType::Temperature),
+            measurement.get(SensorType::Humidity));
+        if mold_risk != self.mold_risk {
+            self.mold_risk = mold_risk;
+            logger.log(timestamp, "mold", format_args!("{},{:.1},{:.1}",
+                mold_risk.label(),
+                self.mold.score(),
+                self.mold.wet_hours()));
+        }
+
+        if let Some(event) = self.events.update(timestamp, co2,
+            measurement.get(SensorType::Temperature),
+            measurement.get_corrected(SensorType::AtmPressure)) {
+            logger.log(timestamp, "event", format_args!("{}", event.kind.label()));
+        }
+
+        // 日が変わったら前日のCO2濃度の区分ごとの時間[s]を記録する
+        if let Some(bands) = self.exposure.update(timestamp, co2) {
+            logger.log(timestamp, "co2bands", format_args!("{},{},{},{},{},{}",
+                bands.day,
+                bands.seconds[0],
+                bands.seconds[1],
+                bands.seconds[2],
+                bands.seconds[3],
+                bands.missing));
+        }
+
+        if let Some(estimate) = self.ventilation.update(timestamp, co2) {
+            logger.log(timestamp, "ach", format_args!("{:.2},{:.3},{},{},{:.0},{:.0}",
+                estimate.ach,
+                estimate.r_squared,
+                estimate.confidence.label(),
+                estimate.duration,
+                estimate.start_ppm,
+                estimate.end_ppm));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! user annotations for wio_umwelt_monitor
+
+// 長押しで付ける印の種類
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnnotationCategory {
+    WindowOpened,
+    WindowClosed,
+    MeetingStarted,
+    MeetingEnded,
+    Note
+}
+
+// ボタンで付けた印
+#[derive(Debug, Copy, Clone)]
+pub struct Annotation {
+    pub timestamp: u32,
+    pub category: AnnotationCategory
+}
+
+impl AnnotationCategory {
+    // 選ぶときの並び順
+    pub const ALL: [AnnotationCategory; 5] = [
+        AnnotationCategory::WindowOpened,
+        AnnotationCategory::WindowClosed,
+        AnnotationCategory::MeetingStarted,
+        AnnotationCategory::MeetingEnded,
+        AnnotationCategory::Note
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnnotationCategory::WindowOpened => "window opened",
+            AnnotationCategory::WindowClosed => "window closed",
+            AnnotationCategory::MeetingStarted => "meeting started",
+            AnnotationCategory::MeetingEnded => "meeting ended",
+            AnnotationCategory::Note => "note"
+        }
+    }
+
+    // グラフに添える1文字
+    pub fn marker(&self) -> &'static str {
+        match self {
+            AnnotationCategory::WindowOpened => "o",
+            AnnotationCategory::WindowClosed => "c",
+            AnnotationCategory::MeetingStarted => "m",
+            AnnotationCategory::MeetingEnded => "e",
+            AnnotationCategory::Note => "n"
+        }
+    }
+}
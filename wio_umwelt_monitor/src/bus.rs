@@ -0,0 +1,65 @@
+//! shared I2C bus for wio_umwelt_monitor
+
+use wio_terminal as wio;
+
+use core::cell::RefCell;
+use cortex_m::interrupt::{self, Mutex};
+use wio::hal::hal::blocking::i2c::{Read, Write, WriteRead};
+
+
+// 複数のドライバや割り込みハンドラから共有するI2Cバス
+pub struct SharedBus<I2C> {
+    bus: Mutex<RefCell<I2C>>
+}
+
+// 各ドライバが所有するバスへのハンドル
+// アクセスはクリティカルセクション内で行うので割り込みとも競合しない
+pub struct I2cProxy<'a, I2C> {
+    bus: &'a Mutex<RefCell<I2C>>
+}
+
+impl<I2C> SharedBus<I2C> {
+    pub fn new(i2c: I2C) -> SharedBus<I2C> {
+        SharedBus {
+            bus: Mutex::new(RefCell::new(i2c))
+        }
+    }
+
+    pub fn acquire(&self) -> I2cProxy<'_, I2C> {
+        I2cProxy {
+            bus: &self.bus
+        }
+    }
+}
+
+impl<'a, I2C> Clone for I2cProxy<'a, I2C> {
+    fn clone(&self) -> Self {
+        I2cProxy {
+            bus: self.bus
+        }
+    }
+}
+
+impl<'a, I2C: Write> Write for I2cProxy<'a, I2C> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        interrupt::free(|cs| self.bus.borrow(cs).borrow_mut().write(addr, bytes))
+    }
+}
+
+impl<'a, I2C: Read> Read for I2cProxy<'a, I2C> {
+    type Error = I2C::Error;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        interrupt::free(|cs| self.bus.borrow(cs).borrow_mut().read(addr, buffer))
+    }
+}
+
+impl<'a, I2C: WriteRead> WriteRead for I2cProxy<'a, I2C> {
+    type Error = I2C::Error;
+
+    fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+        interrupt::free(|cs| self.bus.borrow(cs).borrow_mut().write_read(addr, bytes, buffer))
+    }
+}
@@ -0,0 +1,210 @@
+//! per-channel linear calibration for wio_umwelt_monitor
+
+use crate::nvm::*;
+use crate::sensor::*;
+
+// 校正できるチャネル（センサが測るチャネル）
+pub const CALIBRATED_KINDS: [SensorType; 4] = [
+    SensorType::Temperature,
+    SensorType::Humidity,
+    SensorType::Co2Concentration,
+    SensorType::AtmPressure
+];
+
+// 保存する形式の先頭の印（"WCAL"）と版
+const MAGIC: u32 = 0x5743_414c;
+const VERSION: u32 = 1;
+// 印、版、チャネルごとのオフセットとゲイン、チェックサム
+pub const ENCODED_LEN: usize = 8 + 8 * CALIBRATED_KINDS.len() + 4;
+
+// 画面で1回に変えるゲインの量
+const GAIN_STEP: f32 = 0.005;
+
+// 1チャネル分の校正（読んだ値 * gain + offset を使う）
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Calibration {
+    pub offset: f32,
+    pub gain: f32
+}
+
+// 画面で変える項目
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CalibrationField {
+    Offset,
+    Gain
+}
+
+// 校正を保存したかどうか
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CalibrationStatus {
+    Saved,
+    Edited,
+    SaveFailed
+}
+
+// CALIBRATED_KINDSの順のチャネルごとの校正
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CalibrationTable {
+    entries: [Calibration; CALIBRATED_KINDS.len()]
+}
+
+impl Calibration {
+    // 何もしない校正
+    pub const IDENTITY: Calibration = Calibration {
+        offset: 0.0,
+        gain: 1.0
+    };
+
+    pub fn apply(&self, value: f32) -> f32 {
+        value * self.gain + self.offset
+    }
+
+    // ゲインは正の有限の値に限る（0や負では測定値の変化を表せない）
+    pub fn is_valid(&self) -> bool {
+        self.offset.is_finite() && self.gain.is_finite() && 0.0 < self.gain
+    }
+}
+
+impl CalibrationStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CalibrationStatus::Saved => "saved",
+            CalibrationStatus::Edited => "not saved",
+            CalibrationStatus::SaveFailed => "save failed"
+        }
+    }
+}
+
+impl CalibrationTable {
+    pub const fn new() -> CalibrationTable {
+        CalibrationTable {
+            entries: [Calibration::IDENTITY; CALIBRATED_KINDS.len()]
+        }
+    }
+
+    pub fn get(&self, kind: SensorType) -> Option<Calibration> {
+        index_of(kind).map(|i| self.entries[i])
+    }
+
+    pub fn set(&mut self, kind: SensorType, calibration: Calibration) -> Result<(), ()> {
+        match index_of(kind) {
+            Some(i) if calibration.is_valid() => {
+                self.entries[i] = calibration;
+                Ok(())
+            },
+            _ => Err(())
+        }
+    }
+
+    // 画面での操作でオフセットかゲインをsteps段だけ変える
+    pub fn adjust(&mut self, kind: SensorType, field: CalibrationField, steps: i32) {
+        let i = match index_of(kind) {
+            Some(i) => i,
+            None => return
+        };
+        let entry = &mut self.entries[i];
+
+        match field {
+            CalibrationField::Offset => {
+                let step = offset_step(kind);
+                entry.offset = libm::roundf(entry.offset / step + steps as f32) * step;
+            },
+            CalibrationField::Gain => {
+                let gain = libm::roundf(entry.gain / GAIN_STEP + steps as f32) * GAIN_STEP;
+                entry.gain = gain.max(GAIN_STEP);
+            }
+        }
+    }
+
+    // 内蔵フラッシュから読む（保存されていないか壊れていればNone）
+    pub fn load(nvm: &Nvm) -> Option<CalibrationTable> {
+        let mut bytes = [0u8; ENCODED_LEN];
+        nvm.read(&mut bytes);
+
+        CalibrationTable::decode(&bytes)
+    }
+
+    pub fn save(&self, nvm: &mut Nvm) -> Result<(), NvmError> {
+        nvm.write(&self.encode())
+    }
+
+    fn encode(&self) -> [u8; ENCODED_LEN] {
+        let mut bytes = [0u8; ENCODED_LEN];
+
+        bytes[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        for (i, entry) in self.entries.iter().enumerate() {
+            let at = 8 + 8 * i;
+            bytes[at..at + 4].copy_from_slice(&entry.offset.to_le_bytes());
+            bytes[at + 4..at + 8].copy_from_slice(&entry.gain.to_le_bytes());
+        }
+
+        let checksum = checksum(&bytes[..ENCODED_LEN - 4]);
+        bytes[ENCODED_LEN - 4..].copy_from_slice(&checksum.to_le_bytes());
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8; ENCODED_LEN]) -> Option<CalibrationTable> {
+        let word = |at: usize| [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]];
+
+        if u32::from_le_bytes(word(0)) != MAGIC || u32::from_le_bytes(word(4)) != VERSION
+            || u32::from_le_bytes(word(ENCODED_LEN - 4)) != checksum(&bytes[..ENCODED_LEN - 4]) {
+            return None;
+        }
+
+        let mut table = CalibrationTable::new();
+        for (i, entry) in table.entries.iter_mut().enumerate() {
+            let at = 8 + 8 * i;
+            *entry = Calibration {
+                offset: f32::from_le_bytes(word(at)),
+                gain: f32::from_le_bytes(word(at + 4))
+            };
+
+            if !entry.is_valid() {
+                return None;
+            }
+        }
+
+        Some(table)
+    }
+}
+
+impl Default for CalibrationTable {
+    fn default() -> Self {
+        CalibrationTable::new()
+    }
+}
+
+// シリアルコンソールで使うチャネルの名前
+pub fn channel_key(kind: SensorType) -> &'static str {
+    match kind {
+        SensorType::Temperature => "temp",
+        SensorType::Humidity => "humid",
+        SensorType::Co2Concentration => "co2",
+        SensorType::AtmPressure => "atm",
+        _ => ""
+    }
+}
+
+pub fn channel_from_key(key: &str) -> Option<SensorType> {
+    CALIBRATED_KINDS.iter().copied().find(|kind| channel_key(*kind) == key)
+}
+
+// 画面で1回に変えるオフセットの量（センサの分解能程度）
+fn offset_step(kind: SensorType) -> f32 {
+    match kind {
+        SensorType::Humidity => 0.5,
+        SensorType::Co2Concentration => 5.0,
+        _ => 0.1
+    }
+}
+
+fn index_of(kind: SensorType) -> Option<usize> {
+    CALIBRATED_KINDS.iter().position(|calibrated| *calibrated == kind)
+}
+
+// FNV-1a
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x0100_0193))
+}
@@ -0,0 +1,30 @@
+//! time of day for wio_umwelt_monitor
+
+// 1日の秒数
+pub const SECONDS_PER_DAY: u32 = 24 * 3600;
+
+// 起動からの秒数を日付と時刻に読み替える
+// 電池で保持される時計が無いので、起動したときの時刻を設定で与える
+#[derive(Debug, Copy, Clone)]
+pub struct Clock {
+    // 起動したときの時刻（0時からの秒数）
+    startup: u32
+}
+
+impl Clock {
+    pub const fn new(startup: u32) -> Clock {
+        Clock {
+            startup: startup % SECONDS_PER_DAY
+        }
+    }
+
+    // 起動した日を0とする日数
+    pub fn day(&self, timestamp: u32) -> u32 {
+        (timestamp / SECONDS_PER_DAY) + (timestamp % SECONDS_PER_DAY + self.startup) / SECONDS_PER_DAY
+    }
+
+    // 0時からの秒数
+    pub fn time_of_day(&self, timestamp: u32) -> u32 {
+        (timestamp % SECONDS_PER_DAY + self.startup) % SECONDS_PER_DAY
+    }
+}
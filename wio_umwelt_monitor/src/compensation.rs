@@ -0,0 +1,119 @@
+//! board heat compensation of temperature for wio_umwelt_monitor
+
+// 1回の更新で進める時間の上限[s]（測定が途切れた後に状態が飛ばないように）
+const MAX_STEP: u32 = 600;
+
+// 温度を測るセンサ
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TemperatureSource {
+    Scd30,
+    Bm1383aglv
+}
+
+// センサごとの発熱の影響[℃]と融合の重み
+// 読んだ値からoffset - warmup * exp(-起動からの時間 / warmup_time) + backlight * 点灯の影響を引く
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SourceCompensation {
+    // 十分に温まった後の、バックライトを消しているときのずれ
+    pub offset: f32,
+    // 起動直後にoffsetより小さい分
+    pub warmup: f32,
+    // バックライトを点け続けたときに上乗せされるずれ
+    pub backlight: f32,
+    pub weight: f32
+}
+
+// 温度の補正の設定
+// 実際の値は参照の温度計と並べて合わせる
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CompensationSettings {
+    pub scd30: SourceCompensation,
+    pub bm1383aglv: SourceCompensation,
+    // 起動後に基板が温まる時定数[s]
+    pub warmup_time: f32,
+    // バックライトの点灯・消灯に温度が追いつく時定数[s]
+    pub backlight_time: f32
+}
+
+// 補正の結果（補正前の値は記録に残して元に戻せるようにする）
+#[derive(Debug, Copy, Clone)]
+pub struct CompensatedTemperature {
+    pub scd30: Option<f32>,
+    pub bm1383aglv: Option<f32>,
+    // 補正して融合した温度[℃]
+    pub temperature: f32
+}
+
+// 基板の発熱で高めに出る温度を補正し、2つのセンサの値を重み付きで平均する
+pub struct TemperatureCompensator {
+    settings: CompensationSettings,
+    is_backlight_on: bool,
+    // バックライトの熱が伝わった割合（0から1）
+    backlight_heat: f32,
+    last_timestamp: Option<u32>
+}
+
+impl SourceCompensation {
+    fn bias(&self, warmed: f32, backlight_heat: f32) -> f32 {
+        self.offset - self.warmup * (1.0 - warmed) + self.backlight * backlight_heat
+    }
+}
+
+impl TemperatureCompensator {
+    // 起動時はバックライトが点いている
+    pub fn new(settings: CompensationSettings) -> TemperatureCompensator {
+        TemperatureCompensator {
+            settings,
+            is_backlight_on: true,
+            backlight_heat: 0.0,
+            last_timestamp: None
+        }
+    }
+
+    // バックライトを点けた・消した（それまでの熱の出入りを先に進めておく）
+    pub fn set_backlight(&mut self, timestamp: u32, is_on: bool) {
+        self.advance(timestamp);
+        self.is_backlight_on = is_on;
+    }
+
+    // 各センサの読んだままの温度（欠測はNone）から補正した温度を求める
+    // timestampは起動からの秒数で、温まり方の見積もりに使う
+    pub fn update(&mut self, timestamp: u32, scd30: Option<f32>, bm1383aglv: Option<f32>) -> Option<CompensatedTemperature> {
+        self.advance(timestamp);
+
+        let warmed = 1.0 - libm::expf(-(timestamp as f32) / self.settings.warmup_time);
+        let readings = [(scd30, &self.settings.scd30), (bm1383aglv, &self.settings.bm1383aglv)];
+
+        let mut sum = 0.0;
+        let mut weights = 0.0;
+        for (reading, source) in readings.iter() {
+            if let Some(value) = reading {
+                sum += source.weight * (value - source.bias(warmed, self.backlight_heat));
+                weights += source.weight;
+            }
+        }
+
+        if weights <= 0.0 {
+            return None;
+        }
+
+        Some(CompensatedTemperature {
+            scd30,
+            bm1383aglv,
+            temperature: sum / weights
+        })
+    }
+
+    // バックライトの熱を一次遅れで点灯・消灯の状態に近づける
+    fn advance(&mut self, timestamp: u32) {
+        let dt = match self.last_timestamp {
+            Some(last) => timestamp.wrapping_sub(last).min(MAX_STEP),
+            None => 0
+        };
+        self.last_timestamp = Some(timestamp);
+
+        let target = if self.is_backlight_on { 1.0 } else { 0.0 };
+        let alpha = 1.0 - libm::expf(-(dt as f32) / self.settings.backlight_time);
+        self.backlight_heat += alpha * (target - self.backlight_heat);
+    }
+}
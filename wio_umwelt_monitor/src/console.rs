@@ -0,0 +1,48 @@
+//! serial console commands for wio_umwelt_monitor
+
+use crate::calibration::*;
+use crate::sensor::SensorType;
+
+// 拡張端子のUARTから受け付けるコマンド
+//   cal                           校正を表示する
+//   cal <ch> <offset> <gain>      校正を設定する（chはtemp, humid, co2, atm）
+//   cal <ch> reset                校正を消す
+//   cal save                      校正を内蔵フラッシュに保存する
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CalibrationCommand {
+    Show,
+    Set(SensorType, Calibration),
+    Save
+}
+
+// 1行を解釈する（解釈できなければNone）
+pub fn parse_command(line: &str) -> Option<CalibrationCommand> {
+    let mut words = line.split_whitespace();
+
+    if words.next()? != "cal" {
+        return None;
+    }
+
+    let command = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => CalibrationCommand::Show,
+        (Some("save"), None, _) => CalibrationCommand::Save,
+        (Some(key), Some("reset"), None) => CalibrationCommand::Set(channel_from_key(key)?, Calibration::IDENTITY),
+        (Some(key), Some(offset), Some(gain)) => {
+            let calibration = Calibration {
+                offset: offset.parse().ok()?,
+                gain: gain.parse().ok()?
+            };
+            if !calibration.is_valid() {
+                return None;
+            }
+
+            CalibrationCommand::Set(channel_from_key(key)?, calibration)
+        },
+        _ => return None
+    };
+
+    match words.next() {
+        Some(_) => None,
+        None => Some(command)
+    }
+}
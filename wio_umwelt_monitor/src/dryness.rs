@@ -0,0 +1,25 @@
+//! dryness risk for wio_umwelt_monitor
+
+// 絶対湿度による乾燥の目安（インフルエンザ対策の指針）
+// 7g/m3未満はウイルスが生き残りやすく、11g/m3以上ではほとんど生き残れないとされる
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Dryness {
+    Dry,
+    Caution,
+    Moist
+}
+
+impl Dryness {
+    // absoluteは絶対湿度[g/m3]
+    pub fn from_absolute(absolute: f32) -> Dryness {
+        if absolute < 7.0 {
+            Dryness::Dry
+        }
+        else if absolute < 11.0 {
+            Dryness::Caution
+        }
+        else {
+            Dryness::Moist
+        }
+    }
+}
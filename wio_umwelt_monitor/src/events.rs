@@ -0,0 +1,127 @@
+//! event detection for wio_umwelt_monitor
+
+use heapless::consts::*;
+use heapless::Vec;
+
+use history::*;
+
+// 変化を調べる期間[s]
+const WINDOW_SPAN: u32 = 120;
+// 調べる期間の測定値の数の上限
+const MAX_WINDOW_SAMPLES: usize = 16;
+// 窓を開けたとみなす、期間中の最大値からのCO2濃度[ppm]と温度[℃]の低下
+const WINDOW_CO2_DROP: f32 = 100.0;
+const WINDOW_TEMPERATURE_DROP: f32 = 0.3;
+// ドアの開閉とみなす、直前の数回の平均からの気圧の変化[hPa]
+// 気圧の変化は一瞬なので、測定の瞬間に重ならなければ捉えられない
+const DOOR_PRESSURE_JUMP: f32 = 0.2;
+const DOOR_REFERENCE_SAMPLES: usize = 3;
+// 同じ種類の出来事を続けて記録しない期間[s]
+const WINDOW_HOLDOFF: u32 = 600;
+const DOOR_HOLDOFF: u32 = 60;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EventKind {
+    WindowOpened,
+    DoorSlam
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Event {
+    pub timestamp: u32,
+    pub kind: EventKind
+}
+
+// 測定値の並びから、窓の開放やドアの開閉を見つける
+pub struct EventDetector {
+    co2: DataHistory<MAX_WINDOW_SAMPLES, MAX_WINDOW_SAMPLES>,
+    temperature: DataHistory<MAX_WINDOW_SAMPLES, MAX_WINDOW_SAMPLES>,
+    pressure: DataHistory<MAX_WINDOW_SAMPLES, MAX_WINDOW_SAMPLES>,
+    events: Vec<Event, U16>
+}
+
+impl EventKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventKind::WindowOpened => "window_opened",
+            EventKind::DoorSlam => "door"
+        }
+    }
+
+    // グラフに添える1文字
+    pub fn marker(&self) -> &'static str {
+        match self {
+            EventKind::WindowOpened => "W",
+            EventKind::DoorSlam => "D"
+        }
+    }
+}
+
+impl EventDetector {
+    // intervalは測定間隔[s]
+    pub fn new(interval: u32) -> EventDetector {
+        let window = ((WINDOW_SPAN / interval.max(1)) as usize + 1).clamp(2, MAX_WINDOW_SAMPLES);
+
+        EventDetector {
+            // SCD30の上限10000ppmまで0.2ppm刻みで保持する
+            co2: DataHistory::new(window, Encoding::new(6553.5, 0.2)),
+            temperature: DataHistory::new(window, Encoding::new(0.0, 0.01)),
+            pressure: DataHistory::new(window, Encoding::new(1000.0, 0.01)),
+            events: Vec::new()
+        }
+    }
+
+    // 記録した出来事（古い順、最大16件）
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    // pressureはフィルタを通す前の値を渡す（平滑化すると一瞬の変化が消えるため）
+    pub fn update(&mut self, timestamp: u32, co2: Option<f32>, temperature: Option<f32>, pressure: Option<f32>) -> Option<Event> {
+        self.co2.set_new_data(co2);
+        self.temperature.set_new_data(temperature);
+
+        let reference = self.pressure.mean(0, DOOR_REFERENCE_SAMPLES);
+        self.pressure.set_new_data(pressure);
+
+        let is_window = match (self.co2.max(), co2, self.temperature.max(), temperature) {
+            (Some(co2_max), Some(co2), Some(temperature_max), Some(temperature)) =>
+                WINDOW_CO2_DROP <= co2_max - co2 && WINDOW_TEMPERATURE_DROP <= temperature_max - temperature,
+            _ => false
+        };
+        let is_door = match (reference, pressure) {
+            (Some(reference), Some(pressure)) => DOOR_PRESSURE_JUMP <= (pressure - reference).abs(),
+            _ => false
+        };
+
+        if is_window && !self.is_recent(timestamp, EventKind::WindowOpened, WINDOW_HOLDOFF) {
+            self.record(timestamp, EventKind::WindowOpened)
+        }
+        else if is_door && !self.is_recent(timestamp, EventKind::DoorSlam, DOOR_HOLDOFF) {
+            self.record(timestamp, EventKind::DoorSlam)
+        }
+        else {
+            None
+        }
+    }
+
+    fn is_recent(&self, timestamp: u32, kind: EventKind, holdoff: u32) -> bool {
+        self.events.iter()
+            .rev()
+            .find(|event| event.kind == kind)
+            .is_some_and(|event| timestamp.wrapping_sub(event.timestamp) < holdoff)
+    }
+
+    fn record(&mut self, timestamp: u32, kind: EventKind) -> Option<Event> {
+        let event = Event { timestamp, kind };
+
+        // いっぱいなら一番古いものを捨てる
+        if self.events.len() == self.events.capacity() {
+            self.events.rotate_left(1);
+            self.events.pop();
+        }
+        self.events.push(event).ok();
+
+        Some(event)
+    }
+}
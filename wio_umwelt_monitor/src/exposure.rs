@@ -0,0 +1,146 @@
+//! CO2 time-in-band statistics for wio_umwelt_monitor
+
+use heapless::consts::*;
+use heapless::Vec;
+
+use crate::clock::*;
+
+// CO2濃度の区分の数
+pub const BAND_COUNT: usize = 4;
+// 1回の測定で数える時間の上限[s]（測定が途切れた間は欠測として数える）
+const MAX_STEP: u32 = 600;
+
+// 区分と集計する時間帯の設定
+#[derive(Debug, Copy, Clone)]
+pub struct ExposureSettings {
+    // 区分の境界[ppm]（小さい順）
+    pub limits: [f32; BAND_COUNT - 1],
+    // 集計する時間帯[時]（開始が終了より後なら日をまたぐ、同じなら終日）
+    pub work_hours: (u32, u32)
+}
+
+// 1日分の区分ごとの時間[s]
+#[derive(Debug, Copy, Clone)]
+pub struct DailyBands {
+    pub day: u32,
+    pub seconds: [u32; BAND_COUNT],
+    pub missing: u32
+}
+
+// 測定値から日ごとの区分ごとの時間を積み重ねる
+pub struct ExposureTracker {
+    settings: ExposureSettings,
+    clock: Clock,
+    current: DailyBands,
+    // 締めた日の集計（新しい順）
+    days: Vec<DailyBands, U7>,
+    last_timestamp: Option<u32>
+}
+
+impl ExposureSettings {
+    // 時刻が集計する時間帯に入るかどうか
+    pub fn is_work_time(&self, time_of_day: u32) -> bool {
+        let start = self.work_hours.0 * 3600 % SECONDS_PER_DAY;
+        let end = self.work_hours.1 * 3600 % SECONDS_PER_DAY;
+
+        if start < end {
+            start <= time_of_day && time_of_day < end
+        }
+        else if end < start {
+            start <= time_of_day || time_of_day < end
+        }
+        else {
+            true
+        }
+    }
+
+    // 値の区分（0が最も低い）
+    pub fn band(&self, co2: f32) -> usize {
+        self.limits.iter().take_while(|limit| **limit <= co2).count()
+    }
+}
+
+impl DailyBands {
+    fn new(day: u32) -> DailyBands {
+        DailyBands {
+            day,
+            seconds: [0; BAND_COUNT],
+            missing: 0
+        }
+    }
+
+    // 値が得られた時間の合計[s]
+    pub fn total(&self) -> u32 {
+        self.seconds.iter().sum()
+    }
+
+    // 区分の時間の割合（値が得られた時間に対して）
+    pub fn fraction(&self, band: usize) -> Option<f32> {
+        match self.total() {
+            0 => None,
+            total => Some(self.seconds[band] as f32 / total as f32)
+        }
+    }
+}
+
+impl ExposureTracker {
+    pub fn new(settings: ExposureSettings, clock: Clock) -> ExposureTracker {
+        ExposureTracker {
+            settings,
+            clock,
+            current: DailyBands::new(0),
+            days: Vec::new(),
+            last_timestamp: None
+        }
+    }
+
+    pub fn settings(&self) -> &ExposureSettings {
+        &self.settings
+    }
+
+    // 今日の途中までの集計
+    pub fn today(&self) -> &DailyBands {
+        &self.current
+    }
+
+    // 締めた日の集計（新しい順、最大7日分）
+    pub fn days(&self) -> &[DailyBands] {
+        &self.days
+    }
+
+    // 前回からの時間を今回の値の区分に数える
+    // 日が変わったら締めた日の集計を返す
+    pub fn update(&mut self, timestamp: u32, co2: Option<f32>) -> Option<DailyBands> {
+        let step = match self.last_timestamp {
+            Some(last) => timestamp.wrapping_sub(last),
+            None => {
+                self.current = DailyBands::new(self.clock.day(timestamp));
+                0
+            }
+        };
+        self.last_timestamp = Some(timestamp);
+
+        let mut closed = None;
+        let day = self.clock.day(timestamp);
+        if day != self.current.day {
+            closed = Some(self.current);
+
+            if self.days.len() == self.days.capacity() {
+                self.days.pop();
+            }
+            self.days.push(self.current).ok();
+            self.days.rotate_right(1);
+
+            self.current = DailyBands::new(day);
+        }
+
+        if self.settings.is_work_time(self.clock.time_of_day(timestamp)) {
+            match co2 {
+                Some(co2) if step <= MAX_STEP => self.current.seconds[self.settings.band(co2)] += step,
+                _ => self.current.missing += step
+            }
+        }
+
+        closed
+    }
+}
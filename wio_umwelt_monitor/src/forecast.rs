@@ -0,0 +1,47 @@
+//! CO2 forecast for wio_umwelt_monitor
+
+use history::Trend;
+
+// 傾向を求める期間[s]
+pub const FORECAST_WINDOW: u32 = 600;
+// これより緩やかな変化は横ばいとみなす[ppm/min]
+const STEADY_SLOPE: f32 = 2.0;
+// これより先の到達は予測しない[min]
+const FORECAST_HORIZON: f32 = 120.0;
+
+// CO2濃度の見通し
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Co2Outlook {
+    Above,
+    Rising { minutes: u32 },
+    Steady,
+    Falling
+}
+
+impl Co2Outlook {
+    // intervalは傾向を求めた値の間隔[s]、thresholdは注意する濃度[ppm]
+    pub fn from_trend(trend: &Trend, interval: u32, threshold: f32) -> Co2Outlook {
+        let slope = trend.slope * 60.0 / interval as f32;
+
+        if threshold <= trend.level {
+            Co2Outlook::Above
+        }
+        else if slope <= -STEADY_SLOPE {
+            Co2Outlook::Falling
+        }
+        else if slope < STEADY_SLOPE {
+            Co2Outlook::Steady
+        }
+        else {
+            let minutes = (threshold - trend.level) / slope;
+
+            if minutes <= FORECAST_HORIZON {
+                // 1分未満でも「~0 min」とはせず1分とする
+                Co2Outlook::Rising { minutes: (minutes + 0.5).max(1.0) as u32 }
+            }
+            else {
+                Co2Outlook::Steady
+            }
+        }
+    }
+}
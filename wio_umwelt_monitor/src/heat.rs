@@ -0,0 +1,43 @@
+//! heatstroke risk for wio_umwelt_monitor
+
+// 日常生活に関する指針（日本生気象学会）の暑さ指数(WBGT)による温度基準域
+// 21℃未満は運動に関する指針の「ほぼ安全」とする
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HeatRisk {
+    Safe,
+    Caution,
+    Warning,
+    SevereWarning,
+    Danger
+}
+
+impl HeatRisk {
+    pub fn from_wbgt(wbgt: f32) -> HeatRisk {
+        if 31.0 <= wbgt {
+            HeatRisk::Danger
+        }
+        else if 28.0 <= wbgt {
+            HeatRisk::SevereWarning
+        }
+        else if 25.0 <= wbgt {
+            HeatRisk::Warning
+        }
+        else if 21.0 <= wbgt {
+            HeatRisk::Caution
+        }
+        else {
+            HeatRisk::Safe
+        }
+    }
+
+    // ほぼ安全/注意/警戒/厳重警戒/危険
+    pub fn label(&self) -> &'static str {
+        match self {
+            HeatRisk::Safe => "Safe",
+            HeatRisk::Caution => "Caution",
+            HeatRisk::Warning => "Warning",
+            HeatRisk::SevereWarning => "Severe",
+            HeatRisk::Danger => "Danger"
+        }
+    }
+}
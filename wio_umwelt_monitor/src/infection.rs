@@ -0,0 +1,116 @@
+//! airborne infection risk for wio_umwelt_monitor
+
+// 吐く息に加わるCO2濃度[ppm]（Rudnick and Milton, 2003）
+pub const EXHALED_CO2: f32 = 37_500.0;
+// 相対リスク1とする再呼吸率と活動
+// 会話しながら再呼吸率1.5%（外気420ppmならCO2濃度およそ980ppm）を基準にすると、
+// 会話中の事務所の600〜1500ppmがおおよそmodからv.highに収まる（約700ppmでmod、約1550ppmでv.high）
+pub const REFERENCE_REBREATHED: f32 = 0.015;
+pub const REFERENCE_ACTIVITY: Activity = Activity::Speaking;
+
+// 室内での活動（感染者が出す飛沫核の量の目安）
+// main.rsの設定で1つを選ぶので、使われない値もある
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Activity {
+    Resting,
+    Speaking,
+    LoudSpeaking,
+    Exercise
+}
+
+// 相対リスクの段階
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskGrade {
+    Low,
+    Moderate,
+    High,
+    VeryHigh
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct InfectionRisk {
+    // 再呼吸率（室内の空気のうち誰かが吐いた息の割合）
+    pub rebreathed: f32,
+    // REFERENCE_ACTIVITYで再呼吸率REFERENCE_REBREATHEDのときを1とする相対リスク
+    pub relative: f32,
+    pub grade: RiskGrade
+}
+
+// 再呼吸率によるWells-Riley式の簡略版
+//   P = 1 - exp(-f * I * q * t / n)
+// 感染確率が小さい範囲ではPはf * qにほぼ比例するので、その比を相対リスクとする
+#[derive(Debug, Copy, Clone)]
+pub struct InfectionModel {
+    pub baseline: f32,
+    pub activity: Activity
+}
+
+impl Activity {
+    // 安静時を1とする飛沫核の放出量の倍率（おおまかな目安）
+    pub fn emission_factor(&self) -> f32 {
+        match self {
+            Activity::Resting => 1.0,
+            Activity::Speaking => 4.0,
+            Activity::LoudSpeaking => 10.0,
+            Activity::Exercise => 6.0
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Activity::Resting => "resting",
+            Activity::Speaking => "speaking",
+            Activity::LoudSpeaking => "loud speaking",
+            Activity::Exercise => "exercise"
+        }
+    }
+}
+
+impl RiskGrade {
+    pub fn from_relative(relative: f32) -> RiskGrade {
+        if 2.0 <= relative {
+            RiskGrade::VeryHigh
+        }
+        else if 1.0 <= relative {
+            RiskGrade::High
+        }
+        else if 0.5 <= relative {
+            RiskGrade::Moderate
+        }
+        else {
+            RiskGrade::Low
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RiskGrade::Low => "low",
+            RiskGrade::Moderate => "mod",
+            RiskGrade::High => "high",
+            RiskGrade::VeryHigh => "v.high"
+        }
+    }
+}
+
+impl InfectionModel {
+    // baselineは外気のCO2濃度[ppm]
+    pub fn new(baseline: f32, activity: Activity) -> InfectionModel {
+        InfectionModel {
+            baseline,
+            activity
+        }
+    }
+
+    pub fn evaluate(&self, co2: f32) -> InfectionRisk {
+        let rebreathed = ((co2 - self.baseline) / EXHALED_CO2).max(0.0);
+        let reference = REFERENCE_REBREATHED * REFERENCE_ACTIVITY.emission_factor();
+        let relative = rebreathed * self.activity.emission_factor() / reference;
+
+        InfectionRisk {
+            rebreathed,
+            relative,
+            grade: RiskGrade::from_relative(relative)
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! serial logger for wio_umwelt_monitor
+
+use wio_terminal as wio;
+
+use heapless::consts::*;
+use heapless::String;
+
+use core::fmt;
+use core::fmt::Write;
+use wio::hal::hal::serial;
+
+use crate::calibration::*;
+use crate::sensor::*;
+use crate::Uart;
+
+
+// 拡張端子のUARTに1行1件で記録を書き出す
+// 各行は「起動からの秒数,種別,値...」のCSV形式
+// 同じUARTからコンソールのコマンドを1行ずつ受け取る
+pub struct Logger {
+    uart: Uart,
+    line: String<U32>,
+    is_line_ready: bool
+}
+
+impl Logger {
+    pub fn new(uart: Uart) -> Logger {
+        Logger {
+            uart,
+            line: String::new(),
+            is_line_ready: false
+        }
+    }
+
+    // 届いている文字を読む（受信の割り込みは使わないので、手で打つ速さを想定して頻繁に呼ぶ）
+    // 長すぎる行は切り詰める
+    pub fn receive(&mut self) {
+        while let Ok(byte) = serial::Read::read(&mut self.uart) {
+            if self.is_line_ready {
+                continue;
+            }
+
+            match byte {
+                b'\r' | b'\n' => self.is_line_ready = !self.line.is_empty(),
+                0x08 | 0x7f => {
+                    self.line.pop();
+                },
+                byte if byte.is_ascii_graphic() || byte == b' ' => {
+                    self.line.push(byte as char).ok();
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // 受け取り終わった1行
+    pub fn take_line(&mut self) -> Option<String<U32>> {
+        if !self.is_line_ready {
+            return None;
+        }
+
+        self.is_line_ready = false;
+        let line = self.line.clone();
+        self.line.clear();
+
+        Some(line)
+    }
+
+    // 書き出せなかった記録は捨てる（測定を止めないため）
+    pub fn log(&mut self, timestamp: u32, tag: &str, args: fmt::Arguments) {
+        write!(self, "{},{},", timestamp, tag)
+            .and_then(|_| self.write_fmt(args))
+            .and_then(|_| self.write_str("\r\n"))
+            .ok();
+    }
+
+    // 測定値の記録の列の名前（起動時に1回書き出す）
+    pub fn log_columns(&mut self, timestamp: u32, kinds: &[SensorType]) {
+        write!(self, "{},columns", timestamp)
+            .and_then(|_| kinds.iter().try_for_each(|kind| write!(self, ",{}", kind.title())))
+            .and_then(|_| self.write_str("\r\n"))
+            .ok();
+    }
+
+    // 測定値を1行で書き出す（欠測は空欄）
+    pub fn log_measurement(&mut self, measurement: &Measurement, kinds: &[SensorType]) {
+        write!(self, "{},data", measurement.timestamp)
+            .and_then(|_| kinds.iter().try_for_each(|kind| match measurement.get(*kind) {
+                Some(value) => write!(self, ",{:.1}", value),
+                None => self.write_str(",")
+            }))
+            .and_then(|_| self.write_str("\r\n"))
+            .ok();
+    }
+
+    // センサから読んだままの値（校正と温度の補正の前）を1行で書き出す（列はlog_measurement()と同じ、欠測は空欄）
+    pub fn log_raw(&mut self, measurement: &Measurement, kinds: &[SensorType]) {
+        write!(self, "{},raw", measurement.timestamp)
+            .and_then(|_| kinds.iter().try_for_each(|kind| match measurement.get_raw(*kind) {
+                Some(value) => write!(self, ",{:.1}", value),
+                None => self.write_str(",")
+            }))
+            .and_then(|_| self.write_str("\r\n"))
+            .ok();
+    }
+
+    // チャネルごとの校正を1行で書き出す（チャネル名,オフセット,ゲインの繰り返し）
+    // 校正を当てる前の値はlog_raw()の行に残る
+    pub fn log_calibration(&mut self, timestamp: u32, table: &CalibrationTable) {
+        write!(self, "{},calibration", timestamp)
+            .and_then(|_| CALIBRATED_KINDS.iter().try_for_each(|kind| match table.get(*kind) {
+                Some(calibration) => write!(self, ",{},{},{}", channel_key(*kind), calibration.offset, calibration.gain),
+                None => Ok(())
+            }))
+            .and_then(|_| self.write_str("\r\n"))
+            .ok();
+    }
+
+    // 温度を補正したときの補正前の値を1行で書き出す（SCD30、BM1383AGLV、補正後の温度、補正前の相対湿度、欠測は空欄）
+    pub fn log_compensation(&mut self, measurement: &Measurement) {
+        let (compensation, humidity) = match measurement.compensation() {
+            Some(compensation) => compensation,
+            None => return
+        };
+
+        write!(self, "{},tcomp", measurement.timestamp)
+            .and_then(|_| [compensation.scd30, compensation.bm1383aglv, Some(compensation.temperature), humidity]
+                .iter()
+                .try_for_each(|value| match value {
+                    Some(value) => write!(self, ",{:.2}", value),
+                    None => self.write_str(",")
+                }))
+            .and_then(|_| self.write_str("\r\n"))
+            .ok();
+    }
+}
+
+impl fmt::Write for Logger {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            nb::block!(serial::Write::write(&mut self.uart, byte)).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@
 use panic_halt as _;
 use wio_terminal as wio;
 
+use core::mem::MaybeUninit;
 use cortex_m::peripheral::NVIC;
 use wio::hal::clock::GenericClockController;
 use wio::hal::delay::Delay;
@@ -19,12 +20,164 @@ use wio::prelude::*;
 
 use scd30::*;
 use bm1383aglv::*;
+use filter::FilterConfig;
+
+mod analysis;
+use analysis::*;
+
+mod annotation;
+
+mod bus;
+use bus::*;
+
+mod calibration;
+use calibration::*;
+
+mod clock;
+use clock::*;
+
+mod compensation;
+use compensation::*;
+
+mod console;
+use console::*;
+
+mod dryness;
+
+mod events;
+
+mod exposure;
+use exposure::*;
+
+mod forecast;
+
+mod heat;
+use heat::*;
+
+mod infection;
+use infection::*;
+
+mod logger;
+use logger::*;
+
+mod mold;
+use mold::*;
+
+mod nvm;
+use nvm::*;
+
+mod occupancy;
+use occupancy::*;
+
+mod scanner;
+use scanner::*;
+
+mod sensor;
+use sensor::*;
+
+mod ventilation;
 
 mod viewer;
 use viewer::*;
 
+mod weather;
+
+// ボタンを読む間隔[ms]と、何回続けて押されていると読んだら長押しとするか
+const BUTTON_POLL_MS: u16 = 250;
+const LONG_PRESS_POLLS: u8 = 4;
+// コンソールの文字を読む間隔[ms]
+const CONSOLE_POLL_MS: u16 = 10;
+
 // defined constant value
 const SENSING_INTERVAL: u16 = 12;
+const DEVICE_LIST_DISPLAY_MS: u16 = 2000;
+const LOG_BAUD_RATE: u32 = 115_200;
+
+// 表示に使う単位
+const DISPLAY_UNITS: [Unit; 2] = [Unit::Celsius, Unit::Hectopascal];
+
+// 外気のCO2濃度[ppm]（換気回数の推定に使う）
+const OUTDOOR_CO2: f32 = 420.0;
+
+// 在室人数を推定する部屋（Noneなら推定しない）
+// 換気回数がNoneなら、CO2濃度の減衰から推定した換気回数を使う
+const ROOM: Option<RoomSettings> = Some(RoomSettings {
+    volume: 60.0,
+    ach: None
+});
+
+// 室内での活動（感染リスクの目安に使う）
+const ACTIVITY: Activity = Activity::Speaking;
+
+// 絶対湿度が乾燥の目安を下回ったら加湿を促す（画面を点ける）
+const HUMIDIFY_ALERT: bool = true;
+
+// カビの生育条件（湿度をNoneにすると温度に応じた等値線を使う）
+// 条件にあった時間がalert_hours続いたら警報とする（画面を点ける）
+const MOLD: MoldSettings = MoldSettings {
+    humidity: None,
+    min_temperature: 5.0,
+    alert_hours: 24.0,
+    surface_offset: 3.0
+};
+
+// 電源を入れたときの時刻[時, 分]（時計が無いので、日ごとの集計はこれを基準にする）
+const STARTUP_TIME: (u32, u32) = (9, 0);
+
+// CO2濃度の区分の境界[ppm]と、区分ごとの時間を集計する時間帯[時]
+const EXPOSURE: ExposureSettings = ExposureSettings {
+    limits: [800.0, 1000.0, 1500.0],
+    work_hours: (9, 18)
+};
+
+// 基板の発熱による温度のずれ[℃]（Noneなら補正しない）
+// 両方のセンサの値を補正し、重み付きで平均した温度から相対湿度も求め直す
+const COMPENSATION: Option<CompensationSettings> = Some(CompensationSettings {
+    scd30: SourceCompensation { offset: 1.5, warmup: 1.0, backlight: 0.5, weight: 1.0 },
+    bm1383aglv: SourceCompensation { offset: 3.0, warmup: 2.0, backlight: 1.5, weight: 0.5 },
+    warmup_time: 1800.0,
+    backlight_time: 600.0
+});
+
+// 測定値を毎回UARTに記録するかどうか
+const LOG_MEASUREMENTS: bool = true;
+// センサから読んだままの値（校正、温度の補正、フィルタの前）も記録するかどうか
+const LOG_RAW_VALUES: bool = true;
+
+// センサが測るチャネルごとのフィルタ（妥当な範囲、1分あたりの変化の上限、中央値の数、平滑化の時定数[s]）
+// 使わない段はNoneか1にする
+const FILTERS: [(SensorType, FilterConfig); 4] = [
+    (SensorType::Temperature, FilterConfig { range: Some((-10.0, 60.0)), max_rate: Some(2.0), median: 3, smoothing: None }),
+    (SensorType::Humidity, FilterConfig { range: Some((0.0, 100.0)), max_rate: Some(10.0), median: 3, smoothing: None }),
+    (SensorType::Co2Concentration, FilterConfig { range: Some((250.0, 10000.0)), max_rate: Some(500.0), median: 3, smoothing: None }),
+    (SensorType::AtmPressure, FilterConfig { range: Some((800.0, 1100.0)), max_rate: Some(1.0), median: 5, smoothing: Some(120.0) })
+];
+
+// CO2濃度がこの値[ppm]に達するまでの時間を予測する
+const CO2_FORECAST_THRESHOLD: f32 = 1000.0;
+
+// 暑さ指数がこの温度基準域以上になったら警報とする（画面を点ける）
+const HEAT_ALERT_LEVEL: HeatRisk = HeatRisk::SevereWarning;
+
+// 設置場所の標高[m]（天気の予報には海面気圧を使う）
+const ALTITUDE: f32 = 0.0;
+
+// 3時間でこの値[hPa]以上気圧が下がったら警報とする（画面を点ける）
+const STORM_ALERT_DROP: f32 = 3.0;
+
+// 数値を表示するチャネル（表示できるのは4行まで）
+// 例えばHumidityをDewPointやDiscomfortIndexに替えられる
+// ここに無いチャネルもグラフでは選べる
+const DISPLAY_ROWS: [SensorType; 4] = [
+    SensorType::Temperature,
+    SensorType::Humidity,
+    SensorType::Co2Concentration,
+    SensorType::AtmPressure
+];
+
+pub type I2cBus = I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>>;
+pub type I2cHandle = I2cProxy<'static, I2cBus>;
+pub type Uart = UART2<Sercom2Pad1<Pb27<PfC>>, Sercom2Pad0<Pb26<PfC>>, (), ()>;
 
 
 // main()関数と割り込みハンドラとで共有するリソース
@@ -33,6 +186,12 @@ struct Ctx {
 }
 static mut CTX: Option<Ctx> = None;
 static mut SECOND: u16 = 0;
+static mut UPTIME: u32 = 0;
+
+// 測定値の履歴
+// 初期値を持たせると.dataに置かれ、約141KBの初期値がフラッシュを使ったうえ起動のたびに複写される
+// ゼロで埋めて.bssに置き、使う前にその場で初期化する
+static mut DATA_SET: MaybeUninit<DataSet> = MaybeUninit::zeroed();
 
 
 #[entry]
@@ -40,16 +199,17 @@ fn main() -> ! {
     const X_TITLE: i32 = 10;
     const X_UNIT: i32 = 240;
     const X_NUM_R: i32 = 230;
-    const Y_TMP: i32 = 5;
-    const Y_HUM: i32 = 55;
-    const Y_CO2: i32 = 105;
-    const Y_ATM: i32 = 155;
+    const Y_ROWS_TOP: i32 = 5;
+    const Y_ROWS_BOTTOM: i32 = 205;
     const Y_GRAPH: i32 = 199;
     const HEIGHT_GRAPH: i32 = 40;
 
-    let coordinates = Coordinates::new(X_TITLE, X_UNIT, X_NUM_R, Y_TMP, Y_HUM, Y_CO2, Y_ATM, Y_GRAPH, HEIGHT_GRAPH);
+    let coordinates = Coordinates::new(X_TITLE, X_UNIT, X_NUM_R, Y_ROWS_TOP, Y_ROWS_BOTTOM, Y_GRAPH, HEIGHT_GRAPH);
 
-    let mut view: Viewer = Viewer::new(coordinates);
+    // ゼロで埋めたDataSetはresetすれば使える
+    let history = unsafe { (*core::ptr::addr_of_mut!(DATA_SET)).assume_init_mut() };
+    history.reset(SENSING_INTERVAL as u32);
+    let mut view: Viewer = Viewer::new(coordinates, history);
 
     loop {
         umwelt_monitor(&mut view);
@@ -73,6 +233,10 @@ fn umwelt_monitor(view: &mut Viewer) {
     let button_right = pins.button1.into_floating_input(&mut pins.port);
     let button_center = pins.button2.into_floating_input(&mut pins.port);
     let button_left = pins.button3.into_floating_input(&mut pins.port);
+    let button_up = pins.switch_u.into_floating_input(&mut pins.port);
+    let button_down = pins.switch_x.into_floating_input(&mut pins.port);
+    let button_back = pins.switch_b.into_floating_input(&mut pins.port);
+    let button_forward = pins.switch_y.into_floating_input(&mut pins.port);
 
     let core = CorePeripherals::take().unwrap();
     let mut clocks = GenericClockController::with_external_32kosc(
@@ -104,9 +268,22 @@ fn umwelt_monitor(view: &mut Viewer) {
         &mut delay
     ).unwrap();
 
+    // 拡張端子のUARTに解析結果を記録する
+    let uart = wio::UART {
+        tx: pins.txd,
+        rx: pins.rxd
+    };
+    let mut logger = Logger::new(uart.init(
+        &mut clocks,
+        LOG_BAUD_RATE.hz(),
+        peripherals.SERCOM2,
+        &mut peripherals.MCLK,
+        &mut pins.port
+    ));
+
     // I2Cドライバオブジェクトを初期化する
     let gclk0 = &clocks.gclk0();
-    let mut i2c: I2CMaster3<Sercom3Pad0<Pa17<PfD>>, Sercom3Pad1<Pa16<PfD>>> = I2CMaster3::new(
+    let i2c: I2cBus = I2CMaster3::new(
         &clocks.sercom3_core(&gclk0).unwrap(),
         400.khz(),
         peripherals.SERCOM3,
@@ -115,33 +292,92 @@ fn umwelt_monitor(view: &mut Viewer) {
         pins.i2c1_scl.into_pad(&mut pins.port)
     );
 
+    // 各ドライバがハンドルを持てるようにI2Cバスを共有する
+    let bus: &'static SharedBus<I2cBus> = cortex_m::singleton!(: SharedBus<I2cBus> = SharedBus::new(i2c)).unwrap();
+
+    // 接続されているセンサを探す
+    let mut devices = scan_bus(bus);
+    print_detected_devices(&mut display, &devices);
+
     // CO2センサを初期化する
-    let mut sensor = SCD30::new();
-    let mut is_sensor_initialized = true;
-    if let Err(_) = sensor.init(&mut i2c, SENSING_INTERVAL){
-        is_sensor_initialized = false;
-    }
+    let mut sensor = SCD30::new(bus.acquire());
+    if devices.contains(DeviceKind::Scd30) {
+        let is_sensor_initialized = sensor.init(SENSING_INTERVAL).is_ok()
+            && sensor.set_auto_calibration(true).is_ok();
 
-    if is_sensor_initialized {
-        if let Err(_) = sensor.set_auto_calibration(&mut i2c, true){
-            is_sensor_initialized = false;
+        if !is_sensor_initialized {
+            devices.remove(DeviceKind::Scd30);
         }
     }
 
     // 気圧センサを初期化する
-    let mut barometer = BM1383AGLV::new();
-    let mut is_barometer_enabled = true;
-    if let Err(_code) = barometer.init(&mut i2c, &mut delay){
-        is_barometer_enabled = false;
+    let mut barometer = BM1383AGLV::new(bus.acquire());
+    if devices.contains(DeviceKind::Bm1383aglv) && barometer.init(&mut delay).is_err() {
+        devices.remove(DeviceKind::Bm1383aglv);
+    }
+
+    // 使えるセンサを登録する（温度は先に登録したSCD30を優先する）
+    let mut registry = SensorRegistry::new();
+    if devices.contains(DeviceKind::Scd30) {
+        registry.register(&mut sensor).ok();
     }
+    if devices.contains(DeviceKind::Bm1383aglv) {
+        registry.register(&mut barometer).ok();
+    }
+
+    for (kind, config) in FILTERS.iter() {
+        registry.set_filter(*kind, *config).ok();
+    }
+    if let Some(settings) = COMPENSATION {
+        registry.set_compensation(settings);
+    }
+
+    // 内蔵フラッシュに保存した校正を読む（無ければ校正しない）
+    let mut nvm = Nvm::new(peripherals.NVMCTRL);
+    let calibration_status = match CalibrationTable::load(&nvm) {
+        Some(table) => {
+            *registry.calibration_mut() = table;
+            CalibrationStatus::Saved
+        },
+        None => CalibrationStatus::Edited
+    };
+    view.set_calibration(&mut display, registry.calibration(), calibration_status);
 
-    print_initializing(&mut display, is_sensor_initialized);
+    delay.delay_ms(DEVICE_LIST_DISPLAY_MS);
 
-    if !is_sensor_initialized {
-        // 初期化失敗時はここで止めてしまう
+    print_initializing(&mut display, !registry.is_empty());
+
+    if registry.is_empty() {
+        // 使えるセンサが無いときはここで止めてしまう
         loop {}
     }
 
+    let clock = Clock::new(STARTUP_TIME.0 * 3600 + STARTUP_TIME.1 * 60);
+    view.set_clock(clock);
+    let mut analysis = Analysis::new(OUTDOOR_CO2, ROOM, ACTIVITY, MOLD, EXPOSURE, clock, SENSING_INTERVAL as u32);
+
+    // 解析から求めるチャネルはCO2濃度が測れるときだけ加える
+    let mut kinds = registry.kinds();
+    if kinds.contains(&SensorType::Co2Concentration) {
+        for kind in analysis.kinds() {
+            kinds.push(*kind).ok();
+        }
+    }
+
+    view.set_channels(&kinds, &DISPLAY_ROWS);
+    if LOG_MEASUREMENTS {
+        logger.log_columns(unsafe { UPTIME }, &kinds);
+    }
+    logger.log_calibration(unsafe { UPTIME }, registry.calibration());
+    for unit in DISPLAY_UNITS.iter() {
+        view.set_display_unit(*unit);
+    }
+    view.set_heat_alert_level(HEAT_ALERT_LEVEL);
+    view.set_co2_threshold(CO2_FORECAST_THRESHOLD);
+    view.set_altitude(ALTITUDE);
+    view.set_storm_alert_drop(STORM_ALERT_DROP);
+    view.set_humidify_alert(HUMIDIFY_ALERT);
+
     // 数値以外の変動しない表示を描画
     view.print_labels(&mut display);
 
@@ -176,31 +412,138 @@ fn umwelt_monitor(view: &mut Viewer) {
 
     let mut is_lcd_on = true;
     let mut updated_second:u16 = 0;
+    let mut was_alert = false;
+    let mut click_polls: u8 = 0;
 
     loop {
         led.set_high().unwrap();
 
-        let (is_available, tmp, hum, co2, atm) = get_sensor_value(&mut i2c, &mut sensor, &mut barometer, is_barometer_enabled);
-        if is_available {
-            view.update(&mut display, tmp, hum, co2, atm);
+        let timestamp = unsafe { UPTIME };
+        let mut measurement = registry.sample(timestamp);
+
+        // どのセンサもまだ測定中のときは次の周期を待つ
+        if measurement.is_updated() {
+            analysis.update(&mut measurement, &mut logger);
+            if LOG_MEASUREMENTS {
+                logger.log_measurement(&measurement, &kinds);
+                if LOG_RAW_VALUES {
+                    logger.log_raw(&measurement, &kinds);
+                    logger.log_compensation(&measurement);
+                }
+            }
+            view.update(&mut display, &measurement, &analysis);
         }
 
+        // 暑さ指数が警報の温度基準域に達したとき、気圧が急に下がったとき、
+        // カビの生育条件が続いたとき、乾燥しているときは画面を点ける
+        let is_alert = view.is_heat_alert() || view.is_storm_alert() || view.is_humidify_alert()
+            || analysis.mold.risk() == MoldRisk::Sustained;
+        if is_alert && !was_alert && !is_lcd_on {
+            backlight.set_high().unwrap();
+            is_lcd_on = true;
+            registry.set_backlight(timestamp, true);
+        }
+        was_alert = is_alert;
+
         led.set_low().unwrap();
 
         loop {
-            if is_lcd_on {
+            // 押し込みは離したときに短押しか長押しかを決める
+            // 長押しで印の種類の一覧を開き、一覧を開いている間の短押しで印を付ける
+            let is_click = button.is_low().unwrap();
+            let is_short_click = !is_click && 0 < click_polls && click_polls < LONG_PRESS_POLLS;
+            let is_long_click = is_click && click_polls + 1 == LONG_PRESS_POLLS;
+            click_polls = if is_click { click_polls.saturating_add(1) } else { 0 };
+
+            if is_lcd_on && view.is_annotating() {
+                if is_short_click {
+                    let timestamp = unsafe { UPTIME };
+                    if let Some(annotation) = view.confirm_annotation(&mut display, &analysis, timestamp) {
+                        logger.log(timestamp, "annotation", format_args!("{}", annotation.category.label()));
+                    }
+                }
+                if button_down.is_low().unwrap() {
+                    view.next_category(&mut display);
+                }
+                if button_up.is_low().unwrap() {
+                    view.prev_category(&mut display);
+                }
+                if button_back.is_low().unwrap() {
+                    view.cancel_annotation(&mut display, &analysis);
+                }
+            }
+            else if is_lcd_on {
                 if button_right.is_low().unwrap() {
                     backlight.set_low().unwrap();
                     is_lcd_on = false;
+                    registry.set_backlight(unsafe { UPTIME }, false);
                 }
-                if button.is_low().unwrap() {
+                if is_short_click {
                     view.next_mode(&mut display);
+                    view.next_calibration_field(&mut display);
+                }
+                if is_long_click {
+                    view.begin_annotation(&mut display);
+                }
+                if button_center.is_low().unwrap() {
+                    view.next_unit(&mut display);
+                    if view.calibration_field().is_some() {
+                        let status = save_calibration(&mut nvm, registry.calibration(), &mut logger);
+                        view.set_calibration(&mut display, registry.calibration(), status);
+                    }
+                }
+                if button_left.is_low().unwrap() {
+                    view.next_span(&mut display);
+                }
+                if button_down.is_low().unwrap() {
+                    view.next_screen(&mut display, &analysis);
+                }
+                if button_up.is_low().unwrap() {
+                    view.prev_screen(&mut display, &analysis);
+                }
+                if button_back.is_low().unwrap() {
+                    view.prev_day(&mut display);
+                    if let Some((kind, field)) = view.calibration_field() {
+                        registry.calibration_mut().adjust(kind, field, -1);
+                        logger.log_calibration(unsafe { UPTIME }, registry.calibration());
+                        view.set_calibration(&mut display, registry.calibration(), CalibrationStatus::Edited);
+                    }
+                }
+                if button_forward.is_low().unwrap() {
+                    view.next_day(&mut display);
+                    if let Some((kind, field)) = view.calibration_field() {
+                        registry.calibration_mut().adjust(kind, field, 1);
+                        logger.log_calibration(unsafe { UPTIME }, registry.calibration());
+                        view.set_calibration(&mut display, registry.calibration(), CalibrationStatus::Edited);
+                    }
                 }
             }
             else {
-                if button_right.is_low().unwrap() || button_center.is_low().unwrap() || button_left.is_low().unwrap() || button.is_low().unwrap() {
+                if button_right.is_low().unwrap() || button_center.is_low().unwrap() || button_left.is_low().unwrap() || button.is_low().unwrap()
+                    || button_up.is_low().unwrap() || button_down.is_low().unwrap()
+                    || button_back.is_low().unwrap() || button_forward.is_low().unwrap() {
                     backlight.set_high().unwrap();
                     is_lcd_on = true;
+                    registry.set_backlight(unsafe { UPTIME }, true);
+                }
+            }
+
+            // コンソールのコマンドを1行ずつ受け付ける
+            if let Some(line) = logger.take_line() {
+                let timestamp = unsafe { UPTIME };
+
+                match parse_command(&line) {
+                    Some(CalibrationCommand::Show) => logger.log_calibration(timestamp, registry.calibration()),
+                    Some(CalibrationCommand::Set(kind, calibration)) => {
+                        registry.calibration_mut().set(kind, calibration).ok();
+                        logger.log_calibration(timestamp, registry.calibration());
+                        view.set_calibration(&mut display, registry.calibration(), CalibrationStatus::Edited);
+                    },
+                    Some(CalibrationCommand::Save) => {
+                        let status = save_calibration(&mut nvm, registry.calibration(), &mut logger);
+                        view.set_calibration(&mut display, registry.calibration(), status);
+                    },
+                    None => logger.log(timestamp, "console", format_args!("unknown command: {}", line))
                 }
             }
 
@@ -211,53 +554,29 @@ fn umwelt_monitor(view: &mut Viewer) {
                 }
             }
 
-            delay.delay_ms(250u16);
+            // ボタンを読む間にコンソールの文字を読んでおく
+            for _ in 0..BUTTON_POLL_MS / CONSOLE_POLL_MS {
+                logger.receive();
+                delay.delay_ms(CONSOLE_POLL_MS);
+            }
         }
     }
 }
 
-// センサデータの取得
-pub fn get_sensor_value( i2c: &mut I2CMaster3<Sercom3Pad0<Pa17<PfD>>,
-                         Sercom3Pad1<Pa16<PfD>>>,
-                         sensor: &mut SCD30,
-                         barometer: &mut BM1383AGLV,
-                         is_barometer_available: bool
-                        )
-    -> (bool, f32, f32, f32, f32)
-{
-    let mut tmp: f32 = 0.0;
-    let mut hum: f32 = 0.0;
-    let mut co2: f32 = 0.0;
-    let mut atm: f32 = 0.0;
-    let mut valid = false;
-
-    if let Ok(is_available) = sensor.is_available(i2c) {
-        if is_available {
-            if let Ok((get_co2, get_tmp, get_hum)) = sensor.get_value(i2c) {
-                if get_co2 < 100.0 {
-                    // なぜかまともなデータが取れないときは無視
-                }
-                else {
-                    valid = true;
-                    tmp = get_tmp;
-                    hum = get_hum;
-                    co2 = get_co2;
-
-                    if is_barometer_available {
-                        if let Ok((_tmp, get_atm)) = barometer.get_value(i2c) {
-
-                            atm = get_atm;
-                        }
-                        else {
-                            valid = false;
-                        }
-                    }
-                }
-            }
+// 校正を内蔵フラッシュに保存して、結果を記録する
+fn save_calibration(nvm: &mut Nvm, table: &CalibrationTable, logger: &mut Logger) -> CalibrationStatus {
+    let timestamp = unsafe { UPTIME };
+
+    match table.save(nvm) {
+        Ok(_) => {
+            logger.log(timestamp, "console", format_args!("calibration saved"));
+            CalibrationStatus::Saved
+        },
+        Err(error) => {
+            logger.log(timestamp, "console", format_args!("calibration save failed: {:?}", error));
+            CalibrationStatus::SaveFailed
         }
     }
-
-    (valid, tmp, hum, co2, atm)
 }
 
 // TC3の割り込みハンドラ（1秒ごとに呼ばれる）
@@ -267,6 +586,7 @@ fn TC3() {
         let ctx = CTX.as_mut().unwrap();
 
         SECOND = (SECOND + 1) % (SENSING_INTERVAL * 5);
+        UPTIME = UPTIME.wrapping_add(1);
 
         // 次のカウントを開始する
         ctx.tc3.wait().unwrap();
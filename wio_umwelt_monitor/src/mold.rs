@@ -0,0 +1,137 @@
+//! mold and condensation risk for wio_umwelt_monitor
+
+use units::*;
+
+// 乾いている間はこの割合でスコアを減らす（湿っていた時間より回復に時間がかかる）
+const DRY_RECOVERY: f32 = 0.5;
+// 1回の測定で進める時間の上限[s]（測定が途切れた間を湿ったままとみなさないため）
+const MAX_STEP: u32 = 600;
+// スコアの上限[h]
+const MAX_SCORE: f32 = 24.0 * 14.0;
+
+// カビの生育条件の設定
+#[derive(Debug, Copy, Clone)]
+pub struct MoldSettings {
+    // この湿度[%RH]以上を生育条件とする（Noneなら温度から求める）
+    pub humidity: Option<f32>,
+    // この温度[℃]未満では生育しないとする
+    pub min_temperature: f32,
+    // スコアがこの時間[h]に達したら警報とする
+    pub alert_hours: f32,
+    // 壁や窓など冷えた面の温度が室温より何℃低いとみなすか（結露の判定に使う）
+    pub surface_offset: f32
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoldRisk {
+    None,
+    Elevated,
+    Sustained
+}
+
+// 生育条件にあった時間を積み重ねる
+// スコアは湿っている間は1時間に1ずつ増え、乾いている間はゆっくり減る
+pub struct MoldTracker {
+    settings: MoldSettings,
+    last_timestamp: Option<u32>,
+    // 生育条件にあった時間の累計[h]
+    wet_hours: f32,
+    score: f32,
+    is_wet: bool,
+    is_condensing: bool
+}
+
+impl MoldRisk {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MoldRisk::None => "none",
+            MoldRisk::Elevated => "elevated",
+            MoldRisk::Sustained => "sustained"
+        }
+    }
+}
+
+impl MoldTracker {
+    pub fn new(settings: MoldSettings) -> MoldTracker {
+        MoldTracker {
+            settings,
+            last_timestamp: None,
+            wet_hours: 0.0,
+            score: 0.0,
+            is_wet: false,
+            is_condensing: false
+        }
+    }
+
+    pub fn wet_hours(&self) -> f32 {
+        self.wet_hours
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+
+    // 今の温湿度がカビの生育条件にあるかどうか
+    pub fn is_wet(&self) -> bool {
+        self.is_wet
+    }
+
+    // 冷えた面で結露しそうかどうか
+    pub fn is_condensing(&self) -> bool {
+        self.is_condensing
+    }
+
+    pub fn risk(&self) -> MoldRisk {
+        if self.settings.alert_hours <= self.score {
+            MoldRisk::Sustained
+        }
+        else if 0.0 < self.score {
+            MoldRisk::Elevated
+        }
+        else {
+            MoldRisk::None
+        }
+    }
+
+    // 生育条件の下限の湿度[%RH]
+    // 設定が無ければ簡略化した等値線（Hukka and Viitanen, 1999）による
+    pub fn critical_humidity(&self, temperature: Celsius) -> f32 {
+        match self.settings.humidity {
+            Some(humidity) => humidity,
+            None if temperature.0 <= 20.0 => {
+                let t = temperature.0;
+                -0.00267 * t * t * t + 0.160 * t * t - 3.13 * t + 100.0
+            },
+            None => 80.0
+        }
+    }
+
+    // 測定値が得られなかったときは時間だけ進める
+    pub fn update(&mut self, timestamp: u32, temperature: Option<f32>, humidity: Option<f32>) -> MoldRisk {
+        let step = match self.last_timestamp {
+            Some(last) => timestamp.wrapping_sub(last).min(MAX_STEP),
+            None => 0
+        };
+        self.last_timestamp = Some(timestamp);
+
+        let (temperature, humidity) = match (temperature, humidity) {
+            (Some(temperature), Some(humidity)) => (Celsius(temperature), RelativeHumidity(humidity)),
+            _ => return self.risk()
+        };
+
+        self.is_wet = self.settings.min_temperature <= temperature.0
+            && self.critical_humidity(temperature) <= humidity.0;
+        self.is_condensing = temperature.0 - self.settings.surface_offset <= humidity.dew_point(temperature).0;
+
+        let hours = step as f32 / 3600.0;
+        if self.is_wet {
+            self.wet_hours += hours;
+            self.score = (self.score + hours).min(MAX_SCORE);
+        }
+        else {
+            self.score = (self.score - hours * DRY_RECOVERY).max(0.0);
+        }
+
+        self.risk()
+    }
+}
@@ -0,0 +1,105 @@
+//! non-volatile storage in the internal flash for wio_umwelt_monitor
+
+use wio_terminal as wio;
+
+use core::ptr;
+use wio::pac::NVMCTRL;
+
+// 保存に使う領域（内蔵フラッシュ512KBの最後の8KBのブロック）
+// memory.xでプログラムの領域から外し、その先頭をリンカが_nvm_block_startとして渡す
+extern "C" {
+    static _nvm_block_start: u8;
+}
+// 1回に書き込めるのは1ページまで
+const PAGE_SIZE: usize = 512;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NvmError {
+    TooLarge,
+    AddressError,
+    ProgrammingError,
+    LockError,
+    ControllerError
+}
+
+// 内蔵フラッシュの1ブロックを、消して1ページだけ書き込む保存領域として使う
+pub struct Nvm {
+    nvmctrl: NVMCTRL
+}
+
+impl Nvm {
+    pub fn new(nvmctrl: NVMCTRL) -> Nvm {
+        // ページバッファへの書き込みは、ページの書き込みコマンドを出したときだけ反映させる
+        nvmctrl.ctrla.modify(|_, w| w.wmode().man());
+
+        Nvm {
+            nvmctrl
+        }
+    }
+
+    // 保存領域の先頭からbufの長さだけ読む（消したままの領域は0xff）
+    pub fn read(&self, buf: &mut [u8]) {
+        for (i, byte) in buf.iter_mut().take(PAGE_SIZE).enumerate() {
+            *byte = unsafe { ptr::read_volatile((block_address() as usize + i) as *const u8) };
+        }
+    }
+
+    // 保存領域を消してからdataを書き込む
+    pub fn write(&mut self, data: &[u8]) -> Result<(), NvmError> {
+        if PAGE_SIZE < data.len() {
+            return Err(NvmError::TooLarge);
+        }
+
+        let address = block_address();
+
+        self.command(address, |w| w.ur())?;
+        self.command(address, |w| w.eb())?;
+        self.command(address, |w| w.pbc())?;
+
+        // ページバッファには32ビット単位で書く（端数は0xffで埋める）
+        for (i, chunk) in data.chunks(4).enumerate() {
+            let mut word = [0xff; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+
+            unsafe {
+                ptr::write_volatile((address as usize + 4 * i) as *mut u32, u32::from_le_bytes(word));
+            }
+        }
+
+        self.command(address, |w| w.wp())
+    }
+
+    fn command<F>(&mut self, address: u32, cmd: F) -> Result<(), NvmError>
+    where F: for<'w> FnOnce(wio::pac::nvmctrl::ctrlb::CMD_W<'w>) -> &'w mut wio::pac::nvmctrl::ctrlb::W
+    {
+        while self.nvmctrl.status.read().ready().bit_is_clear() {}
+
+        // 前のコマンドのエラーを消しておく
+        self.nvmctrl.intflag.write(|w| w.addre().set_bit().proge().set_bit().locke().set_bit().nvme().set_bit().done().set_bit());
+        self.nvmctrl.addr.write(|w| unsafe { w.addr().bits(address) });
+        self.nvmctrl.ctrlb.write(|w| cmd(w.cmdex().key().cmd()));
+
+        while self.nvmctrl.status.read().ready().bit_is_clear() {}
+
+        let flags = self.nvmctrl.intflag.read();
+        if flags.addre().bit_is_set() {
+            Err(NvmError::AddressError)
+        }
+        else if flags.proge().bit_is_set() {
+            Err(NvmError::ProgrammingError)
+        }
+        else if flags.locke().bit_is_set() {
+            Err(NvmError::LockError)
+        }
+        else if flags.nvme().bit_is_set() {
+            Err(NvmError::ControllerError)
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+fn block_address() -> u32 {
+    ptr::addr_of!(_nvm_block_start) as u32
+}
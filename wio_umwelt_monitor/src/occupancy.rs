@@ -0,0 +1,70 @@
+//! occupancy estimation for wio_umwelt_monitor
+
+use history::*;
+
+// CO2濃度の傾向を求める範囲（測定値の個数）
+const TREND_SAMPLES: usize = 50;
+// 1人あたりのCO2発生量（座って作業する成人）[m3/h]
+const CO2_PER_PERSON: f32 = 0.018;
+// 人数の平滑化の時定数[s]
+const SMOOTHING_TIME: f32 = 300.0;
+
+// 部屋の設定
+#[derive(Debug, Copy, Clone)]
+pub struct RoomSettings {
+    // 容積[m3]
+    pub volume: f32,
+    // 換気回数[回/h]（Noneなら推定した換気回数を使う）
+    pub ach: Option<f32>
+}
+
+// CO2濃度の収支から在室人数を推定する
+//   V * dC/dt = G * N - ACH * V * (C - baseline)
+pub struct OccupancyEstimator {
+    room: RoomSettings,
+    interval: u32,
+    co2: DataHistory<TREND_SAMPLES, TREND_SAMPLES>,
+    smoothed: Option<f32>
+}
+
+impl OccupancyEstimator {
+    // intervalは測定間隔[s]
+    pub fn new(room: RoomSettings, interval: u32) -> OccupancyEstimator {
+        OccupancyEstimator {
+            room,
+            interval,
+            // SCD30の上限10000ppmまで0.2ppm刻みで保持する
+            co2: DataHistory::new(TREND_SAMPLES, Encoding::new(6553.5, 0.2)),
+            smoothed: None
+        }
+    }
+
+    // CO2濃度を1つ加えて人数を推定し直す（欠測はNone）
+    // estimated_achは推定した換気回数で、部屋の設定に換気回数が無いときに使う
+    pub fn update(&mut self, co2: Option<f32>, baseline: f32, estimated_ach: Option<f32>) -> Option<f32> {
+        self.co2.set_new_data(co2);
+
+        let ach = self.room.ach.or(estimated_ach);
+        let (trend, ach) = match (self.co2.trend(TREND_SAMPLES), ach) {
+            (Some(trend), Some(ach)) => (trend, ach),
+            _ => {
+                self.smoothed = None;
+                return None;
+            }
+        };
+
+        // 1時間あたりの濃度変化[ppm/h]
+        let rate = trend.slope * 3600.0 / self.interval as f32;
+        let generation = self.room.volume * (rate + ach * (trend.level - baseline)) * 1.0e-6;
+        let people = (generation / CO2_PER_PERSON).max(0.0);
+
+        let alpha = (self.interval as f32 / SMOOTHING_TIME).min(1.0);
+        let smoothed = match self.smoothed {
+            Some(smoothed) => smoothed + alpha * (people - smoothed),
+            None => people
+        };
+        self.smoothed = Some(smoothed);
+
+        Some(smoothed)
+    }
+}
@@ -0,0 +1,110 @@
+//! I2C bus scanner for wio_umwelt_monitor
+
+use wio_terminal as wio;
+
+use heapless::consts::*;
+use heapless::Vec;
+use wio::prelude::*;
+
+use scd30::*;
+use bm1383aglv::*;
+
+use crate::bus::SharedBus;
+use crate::I2cBus;
+
+// 7bitアドレスのうち予約されていない範囲
+const SCAN_ADDRESS_FIRST: u8 = 0x08;
+const SCAN_ADDRESS_LAST: u8 = 0x77;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DeviceKind {
+    Scd30,
+    Bm1383aglv,
+    Unknown
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DetectedDevice {
+    pub address: u8,
+    pub kind: DeviceKind
+}
+
+pub struct DetectedDevices {
+    devices: Vec<DetectedDevice, U16>
+}
+
+impl DeviceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceKind::Scd30 => "SCD30",
+            DeviceKind::Bm1383aglv => "BM1383AGLV",
+            DeviceKind::Unknown => "unknown"
+        }
+    }
+}
+
+impl DetectedDevices {
+    pub fn new() -> DetectedDevices {
+        DetectedDevices {
+            devices: Vec::new()
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DetectedDevice> {
+        self.devices.iter()
+    }
+
+    pub fn contains(&self, kind: DeviceKind) -> bool {
+        self.devices.iter().any(|device| device.kind == kind)
+    }
+
+    // 初期化に失敗したデバイスを利用対象から外す
+    pub fn remove(&mut self, kind: DeviceKind) {
+        for device in self.devices.iter_mut() {
+            if device.kind == kind {
+                device.kind = DeviceKind::Unknown;
+            }
+        }
+    }
+}
+
+// バス上の全アドレスに問い合わせ、応答したデバイスをIDレジスタで識別する
+// 共有バス経由なので、起動後に再スキャンすることもできる
+pub fn scan_bus(bus: &SharedBus<I2cBus>) -> DetectedDevices {
+    let mut detected = DetectedDevices::new();
+    let mut i2c = bus.acquire();
+
+    for address in SCAN_ADDRESS_FIRST..=SCAN_ADDRESS_LAST {
+        if i2c.write(address, &[]).is_err() {
+            continue;
+        }
+
+        let kind = identify(bus, address);
+
+        if detected.devices.push(DetectedDevice { address, kind }).is_err() {
+            break;
+        }
+    }
+
+    detected
+}
+
+fn identify(bus: &SharedBus<I2cBus>, address: u8) -> DeviceKind {
+    match address {
+        SCD30_ADDRESS => {
+            if SCD30::new(bus.acquire()).read_firmware_version().is_ok() {
+                DeviceKind::Scd30
+            }
+            else {
+                DeviceKind::Unknown
+            }
+        },
+        BM1383AGLV_ADDRESS => {
+            match BM1383AGLV::new(bus.acquire()).read_id() {
+                Ok(BM1383AGLV_ID) => DeviceKind::Bm1383aglv,
+                _ => DeviceKind::Unknown
+            }
+        },
+        _ => DeviceKind::Unknown
+    }
+}
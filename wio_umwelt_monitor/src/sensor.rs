@@ -0,0 +1,673 @@
+//! common sensor interface for wio_umwelt_monitor
+
+use heapless::consts::*;
+use heapless::Vec;
+
+use scd30::*;
+use bm1383aglv::*;
+use filter::*;
+use units::*;
+
+use crate::calibration::*;
+use crate::compensation::*;
+use crate::I2cHandle;
+
+
+// 一度に扱えるチャネル数とセンサ数の上限
+pub type MaxChannels = U12;
+pub type MaxSensors = U4;
+const MAX_SENSORS: usize = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorType {
+    Temperature,
+    Humidity,
+    Co2Concentration,
+    AtmPressure,
+    DewPoint,
+    AbsoluteHumidity,
+    DiscomfortIndex,
+    HeatIndex,
+    Wbgt,
+    Occupancy
+}
+
+// 温度と湿度から求めるチャネル
+pub const DERIVED_KINDS: [SensorType; 5] = [
+    SensorType::DewPoint,
+    SensorType::AbsoluteHumidity,
+    SensorType::DiscomfortIndex,
+    SensorType::HeatIndex,
+    SensorType::Wbgt
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Unit {
+    Celsius,
+    Fahrenheit,
+    Percent,
+    GramPerCubicMeter,
+    Dimensionless,
+    Person,
+    Ppm,
+    Hectopascal,
+    Kilopascal,
+    InchOfMercury,
+    MillimeterOfMercury
+}
+
+// 表示する単位に換算した値
+// 単位ごとの型で持ち、表示するまで単位を取り違えないようにする
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UnitValue {
+    Celsius(Celsius),
+    Fahrenheit(Fahrenheit),
+    Percent(RelativeHumidity),
+    GramPerCubicMeter(AbsoluteHumidity),
+    Dimensionless(DiscomfortIndex),
+    Person(f32),
+    Ppm(Ppm),
+    Hectopascal(Hectopascal),
+    Kilopascal(Kilopascal),
+    InchOfMercury(InchOfMercury),
+    MillimeterOfMercury(MillimeterOfMercury)
+}
+
+// チャネルの種類と結び付いた物理量
+pub trait Quantity: Copy {
+    fn kind() -> SensorType;
+    fn value(self) -> f32;
+}
+
+// 値が得られなかった原因
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorError {
+    NotReady,
+    ReadFailure,
+    Implausible
+}
+
+// センサが報告する1チャネル分の値（単位は種類から決まる）
+// 値が得られなかったチャネルはNone
+// valueはフィルタを通した値、rawはセンサから読んだままの値
+// correctedはrawに校正と温度の補正を当てた値で、これをフィルタに通す
+#[derive(Debug, Copy, Clone)]
+pub struct Channel {
+    pub kind: SensorType,
+    pub value: Option<f32>,
+    pub raw: Option<f32>,
+    pub corrected: Option<f32>,
+    // フィルタがこれまでに棄却した値の数
+    pub rejected: u32
+}
+
+// 1回分の測定結果
+#[derive(Clone)]
+pub struct Measurement {
+    pub timestamp: u32,
+    pub error: Option<SensorError>,
+    channels: Vec<Channel, MaxChannels>,
+    // センサごとの読んだままの温度（温度のチャネルには先に登録したセンサの値だけが入る）
+    temperatures: Vec<(TemperatureSource, f32), U2>,
+    // 温度を補正したときの補正前の値
+    compensation: Option<CompensatedTemperature>,
+    // 補正前の温度で測った相対湿度
+    uncompensated_humidity: Option<f32>,
+    // いずれかのセンサが新しく読めたか、読めなかったか（全て測定中ならfalse）
+    is_updated: bool
+}
+
+pub trait EnvironmentalSensor {
+    // このセンサが報告するチャネルの種類
+    fn kinds(&self) -> &'static [SensorType];
+
+    // 測定値を読み出し、kinds()の各チャネルをmeasurementに設定する
+    fn read(&mut self, measurement: &mut Measurement);
+}
+
+// 接続されているセンサの一覧
+pub struct SensorRegistry<'a> {
+    sensors: Vec<&'a mut dyn EnvironmentalSensor, MaxSensors>,
+    filters: Vec<(SensorType, FilterChain), MaxChannels>,
+    // センサごとに最後に読めた値（測定中のセンサはこの値を保つ）
+    held: SampleHold<Measurement, MAX_SENSORS>,
+    compensator: Option<TemperatureCompensator>,
+    calibration: CalibrationTable
+}
+
+impl SensorType {
+    pub fn title(&self) -> &'static str {
+        match self {
+            SensorType::Temperature => "Temp.",
+            SensorType::Humidity => "Humid.",
+            SensorType::Co2Concentration => "CO2",
+            SensorType::AtmPressure => "Atm.",
+            SensorType::DewPoint => "Dew Pt.",
+            SensorType::AbsoluteHumidity => "Abs.H.",
+            SensorType::DiscomfortIndex => "THI",
+            SensorType::HeatIndex => "H.I.",
+            SensorType::Wbgt => "WBGT",
+            SensorType::Occupancy => "People"
+        }
+    }
+
+    pub fn unit(&self) -> Unit {
+        match self {
+            SensorType::Temperature => Unit::Celsius,
+            SensorType::Humidity => Unit::Percent,
+            SensorType::Co2Concentration => Unit::Ppm,
+            SensorType::AtmPressure => Unit::Hectopascal,
+            SensorType::DewPoint | SensorType::HeatIndex | SensorType::Wbgt => Unit::Celsius,
+            SensorType::AbsoluteHumidity => Unit::GramPerCubicMeter,
+            SensorType::DiscomfortIndex => Unit::Dimensionless,
+            SensorType::Occupancy => Unit::Person
+        }
+    }
+
+    // センサが直接測るチャネルかどうか（それ以外は測定値から求める）
+    pub fn is_measured(&self) -> bool {
+        matches!(self, SensorType::Temperature | SensorType::Humidity | SensorType::Co2Concentration | SensorType::AtmPressure)
+    }
+
+    // 温度と湿度から派生チャネルの値を求める
+    fn derive(&self, temperature: Celsius, humidity: RelativeHumidity) -> Option<f32> {
+        match self {
+            SensorType::DewPoint => Some(humidity.dew_point(temperature).0),
+            SensorType::AbsoluteHumidity => Some(humidity.to_absolute(temperature).0),
+            SensorType::DiscomfortIndex => Some(humidity.discomfort_index(temperature).0),
+            SensorType::HeatIndex => Some(humidity.heat_index(temperature).0),
+            SensorType::Wbgt => Some(humidity.wbgt_indoor(temperature).0),
+            _ => None
+        }
+    }
+}
+
+impl Unit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Unit::Celsius => "C",
+            Unit::Fahrenheit => "F",
+            Unit::Percent => "%",
+            Unit::GramPerCubicMeter => "g/m3",
+            Unit::Dimensionless => "",
+            Unit::Person => "ppl",
+            Unit::Ppm => "ppm",
+            Unit::Hectopascal => "hPa",
+            Unit::Kilopascal => "kPa",
+            Unit::InchOfMercury => "inHg",
+            Unit::MillimeterOfMercury => "mmHg"
+        }
+    }
+
+    // 換算元になる単位（センサから得られる単位）
+    pub fn base(&self) -> Unit {
+        match self {
+            Unit::Celsius | Unit::Fahrenheit => Unit::Celsius,
+            Unit::Percent => Unit::Percent,
+            Unit::GramPerCubicMeter => Unit::GramPerCubicMeter,
+            Unit::Dimensionless => Unit::Dimensionless,
+            Unit::Person => Unit::Person,
+            Unit::Ppm => Unit::Ppm,
+            Unit::Hectopascal | Unit::Kilopascal | Unit::InchOfMercury | Unit::MillimeterOfMercury => Unit::Hectopascal
+        }
+    }
+
+    // 同じ物理量の次の単位
+    pub fn next(&self) -> Unit {
+        match self {
+            Unit::Celsius => Unit::Fahrenheit,
+            Unit::Fahrenheit => Unit::Celsius,
+            Unit::Hectopascal => Unit::Kilopascal,
+            Unit::Kilopascal => Unit::InchOfMercury,
+            Unit::InchOfMercury => Unit::MillimeterOfMercury,
+            Unit::MillimeterOfMercury => Unit::Hectopascal,
+            _ => *self
+        }
+    }
+
+}
+
+impl UnitValue {
+    // 種類kindのチャネルの値（換算元の単位）を単位unitに換算する
+    // 同じ物理量の単位でなければ換算元の単位のままにする
+    pub fn new(kind: SensorType, value: f32, unit: Unit) -> UnitValue {
+        let base = match kind {
+            SensorType::Temperature | SensorType::DewPoint | SensorType::HeatIndex | SensorType::Wbgt => UnitValue::Celsius(Celsius(value)),
+            SensorType::Humidity => UnitValue::Percent(RelativeHumidity(value)),
+            SensorType::Co2Concentration => UnitValue::Ppm(Ppm(value)),
+            SensorType::AtmPressure => UnitValue::Hectopascal(Hectopascal(value)),
+            SensorType::AbsoluteHumidity => UnitValue::GramPerCubicMeter(AbsoluteHumidity(value)),
+            SensorType::DiscomfortIndex => UnitValue::Dimensionless(DiscomfortIndex(value)),
+            SensorType::Occupancy => UnitValue::Person(value)
+        };
+
+        match (base, unit) {
+            (UnitValue::Celsius(celsius), Unit::Fahrenheit) => UnitValue::Fahrenheit(celsius.to_fahrenheit()),
+            (UnitValue::Hectopascal(pressure), Unit::Kilopascal) => UnitValue::Kilopascal(pressure.to_kilopascal()),
+            (UnitValue::Hectopascal(pressure), Unit::InchOfMercury) => UnitValue::InchOfMercury(pressure.to_inch_of_mercury()),
+            (UnitValue::Hectopascal(pressure), Unit::MillimeterOfMercury) => UnitValue::MillimeterOfMercury(pressure.to_millimeter_of_mercury()),
+            (base, _) => base
+        }
+    }
+
+    // 表示する数値
+    pub fn value(&self) -> f32 {
+        match *self {
+            UnitValue::Celsius(Celsius(value))
+            | UnitValue::Fahrenheit(Fahrenheit(value))
+            | UnitValue::Percent(RelativeHumidity(value))
+            | UnitValue::GramPerCubicMeter(AbsoluteHumidity(value))
+            | UnitValue::Dimensionless(DiscomfortIndex(value))
+            | UnitValue::Person(value)
+            | UnitValue::Ppm(Ppm(value))
+            | UnitValue::Hectopascal(Hectopascal(value))
+            | UnitValue::Kilopascal(Kilopascal(value))
+            | UnitValue::InchOfMercury(InchOfMercury(value))
+            | UnitValue::MillimeterOfMercury(MillimeterOfMercury(value)) => value
+        }
+    }
+}
+
+impl Quantity for Celsius {
+    fn kind() -> SensorType {
+        SensorType::Temperature
+    }
+
+    fn value(self) -> f32 {
+        self.0
+    }
+}
+
+impl Quantity for RelativeHumidity {
+    fn kind() -> SensorType {
+        SensorType::Humidity
+    }
+
+    fn value(self) -> f32 {
+        self.0
+    }
+}
+
+impl Quantity for Ppm {
+    fn kind() -> SensorType {
+        SensorType::Co2Concentration
+    }
+
+    fn value(self) -> f32 {
+        self.0
+    }
+}
+
+impl Quantity for Hectopascal {
+    fn kind() -> SensorType {
+        SensorType::AtmPressure
+    }
+
+    fn value(self) -> f32 {
+        self.0
+    }
+}
+
+impl Measurement {
+    pub fn new(timestamp: u32) -> Measurement {
+        Measurement {
+            timestamp,
+            error: None,
+            channels: Vec::new(),
+            temperatures: Vec::new(),
+            compensation: None,
+            uncompensated_humidity: None,
+            is_updated: false
+        }
+    }
+
+    // 各センサの温度を記録する（温度の補正に使う）
+    pub fn set_source_temperature(&mut self, source: TemperatureSource, temperature: Celsius) {
+        if self.source_temperature(source).is_none() {
+            self.temperatures.push((source, temperature.0)).ok();
+        }
+    }
+
+    pub fn source_temperature(&self, source: TemperatureSource) -> Option<f32> {
+        self.temperatures.iter()
+            .find(|(temperature_source, _)| *temperature_source == source)
+            .map(|(_, temperature)| *temperature)
+    }
+
+    // 温度を補正したときの補正前の値と、補正前の温度で測った相対湿度
+    pub fn compensation(&self) -> Option<(CompensatedTemperature, Option<f32>)> {
+        self.compensation.map(|compensation| (compensation, self.uncompensated_humidity))
+    }
+
+    // 値を設定する（同じ種類が既にあれば先に設定したほうを優先する）
+    pub fn set<Q: Quantity>(&mut self, quantity: Q) {
+        let kind = Q::kind();
+
+        if self.get_channel(kind).is_none() {
+            self.channels.push(Channel::new(kind, Some(quantity.value()))).ok();
+        }
+    }
+
+    // 値が得られなかったチャネルを原因とともに記録する
+    pub fn set_missing(&mut self, kinds: &[SensorType], cause: SensorError) {
+        for kind in kinds.iter() {
+            if self.get_channel(*kind).is_none() {
+                self.channels.push(Channel::new(*kind, None)).ok();
+            }
+        }
+
+        if self.error.is_none() {
+            self.error = Some(cause);
+        }
+    }
+
+    // 1つのセンサの読み出しを加える（同じ種類が既にあれば先に加えたセンサを優先する）
+    // 先に加えたセンサが欠測にしたチャネルは、後のセンサの値で埋める
+    fn merge(&mut self, reading: &Measurement) {
+        for channel in reading.channels.iter() {
+            match self.channels.iter_mut().find(|merged| merged.kind == channel.kind) {
+                Some(merged) if merged.value.is_none() => *merged = *channel,
+                Some(_) => {},
+                None => {
+                    self.channels.push(*channel).ok();
+                }
+            }
+        }
+
+        for (source, temperature) in reading.temperatures.iter() {
+            self.set_source_temperature(*source, Celsius(*temperature));
+        }
+    }
+
+    pub fn get(&self, kind: SensorType) -> Option<f32> {
+        self.get_channel(kind).and_then(|channel| channel.value)
+    }
+
+    // 温度と湿度の両方が得られていれば派生チャネルの値を求める
+    // どちらかが欠けていれば派生チャネルも欠測にする
+    pub fn derive(&mut self) {
+        let temperature = self.get(SensorType::Temperature).map(Celsius);
+        let humidity = self.get(SensorType::Humidity).map(RelativeHumidity);
+
+        for kind in DERIVED_KINDS.iter() {
+            let value = match (temperature, humidity) {
+                (Some(temperature), Some(humidity)) => kind.derive(temperature, humidity),
+                _ => None
+            };
+
+            self.set_estimate(*kind, value);
+        }
+    }
+
+    // 測定値の履歴などから求めた値を設定する（同じ種類が既にあればそちらを優先する）
+    pub fn set_estimate(&mut self, kind: SensorType, value: Option<f32>) {
+        if self.get_channel(kind).is_none() {
+            self.channels.push(Channel::new(kind, value)).ok();
+        }
+    }
+
+    // センサから読んだままの値（校正と温度の補正の前）
+    pub fn get_raw(&self, kind: SensorType) -> Option<f32> {
+        self.get_channel(kind).and_then(|channel| channel.raw)
+    }
+
+    // 校正と温度の補正を当て、フィルタを通す前の値
+    pub fn get_corrected(&self, kind: SensorType) -> Option<f32> {
+        self.get_channel(kind).and_then(|channel| channel.corrected)
+    }
+
+    // フィルタがこれまでに棄却した値の数（フィルタが無ければ0）
+    pub fn rejected(&self, kind: SensorType) -> u32 {
+        self.get_channel(kind).map_or(0, |channel| channel.rejected)
+    }
+
+    // 読んだままの値に校正を当てる（センサごとの温度にも温度の校正を当てる）
+    // 読んだままの値はrawに残す
+    fn calibrate(&mut self, table: &CalibrationTable) {
+        for channel in self.channels.iter_mut() {
+            if let Some(calibration) = table.get(channel.kind) {
+                channel.corrected = channel.raw.map(|value| calibration.apply(value));
+                channel.value = channel.corrected;
+            }
+        }
+
+        if let Some(calibration) = table.get(SensorType::Temperature) {
+            for (_, temperature) in self.temperatures.iter_mut() {
+                *temperature = calibration.apply(*temperature);
+            }
+        }
+    }
+
+    // 温度を補正して融合した値に置き換え、相対湿度をその温度での値に換算し直す
+    // 相対湿度はSCD30が自身の温度で測ったものとする
+    fn compensate(&mut self, compensator: &mut TemperatureCompensator) {
+        let scd30 = self.source_temperature(TemperatureSource::Scd30);
+        let compensation = match compensator.update(self.timestamp, scd30, self.source_temperature(TemperatureSource::Bm1383aglv)) {
+            Some(compensation) => compensation,
+            None => return
+        };
+        let temperature = compensation.temperature;
+
+        for channel in self.channels.iter_mut() {
+            match (channel.kind, channel.corrected, scd30) {
+                (SensorType::Temperature, _, _) => {
+                    channel.corrected = Some(temperature);
+                    channel.value = Some(temperature);
+                },
+                (SensorType::Humidity, Some(humidity), Some(measured)) => {
+                    let humidity = RelativeHumidity(humidity).at_temperature(Celsius(measured), Celsius(temperature)).0;
+
+                    self.uncompensated_humidity = channel.corrected;
+                    channel.corrected = Some(humidity);
+                    channel.value = Some(humidity);
+                },
+                _ => {}
+            }
+        }
+        // 温度のチャネルが無ければ融合した温度で加える（片方のセンサしか読めなかったときも捨てない）
+        self.set_estimate(SensorType::Temperature, Some(temperature));
+
+        self.compensation = Some(compensation);
+    }
+
+    // 校正と補正を当てた値をフィルタに通す（棄却された値は欠測にする）
+    fn filter(&mut self, kind: SensorType, filter: &mut FilterChain) {
+        let timestamp = self.timestamp;
+
+        if let Some(channel) = self.channels.iter_mut().find(|channel| channel.kind == kind) {
+            channel.value = filter.update(timestamp, channel.corrected).unwrap_or(None);
+            channel.rejected = filter.rejected();
+        }
+    }
+
+    // いずれかのセンサが測定を終えていたかどうか（全て測定中で前回の値を保っただけならfalse）
+    pub fn is_updated(&self) -> bool {
+        self.is_updated
+    }
+
+    fn get_channel(&self, kind: SensorType) -> Option<&Channel> {
+        self.channels.iter().find(|channel| channel.kind == kind)
+    }
+}
+
+impl Channel {
+    fn new(kind: SensorType, value: Option<f32>) -> Channel {
+        Channel {
+            kind,
+            value,
+            raw: value,
+            corrected: value,
+            rejected: 0
+        }
+    }
+}
+
+impl<'a> SensorRegistry<'a> {
+    pub fn new() -> SensorRegistry<'a> {
+        SensorRegistry {
+            sensors: Vec::new(),
+            filters: Vec::new(),
+            held: SampleHold::new(),
+            compensator: None,
+            calibration: CalibrationTable::new()
+        }
+    }
+
+    pub fn register(&mut self, sensor: &'a mut dyn EnvironmentalSensor) -> Result<(), ()> {
+        match self.sensors.push(sensor) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(())
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    // センサが測るチャネルに通すフィルタを設定する
+    pub fn set_filter(&mut self, kind: SensorType, config: FilterConfig) -> Result<(), ()> {
+        if let Some((_, filter)) = self.filters.iter_mut().find(|(filter_kind, _)| *filter_kind == kind) {
+            *filter = FilterChain::new(config);
+            return Ok(());
+        }
+
+        match self.filters.push((kind, FilterChain::new(config))) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(())
+        }
+    }
+
+    // チャネルごとの校正
+    pub fn calibration(&self) -> &CalibrationTable {
+        &self.calibration
+    }
+
+    pub fn calibration_mut(&mut self) -> &mut CalibrationTable {
+        &mut self.calibration
+    }
+
+    // 基板の発熱による温度のずれを補正する
+    pub fn set_compensation(&mut self, settings: CompensationSettings) {
+        self.compensator = Some(TemperatureCompensator::new(settings));
+    }
+
+    // バックライトを点けた・消した（温度の補正に使う）
+    pub fn set_backlight(&mut self, timestamp: u32, is_on: bool) {
+        if let Some(compensator) = self.compensator.as_mut() {
+            compensator.set_backlight(timestamp, is_on);
+        }
+    }
+
+    // 表示するチャネルの種類（同じ種類は先に登録したセンサを優先する）
+    // 温度と湿度が揃っていれば派生チャネルも加える
+    pub fn kinds(&self) -> Vec<SensorType, MaxChannels> {
+        let mut kinds: Vec<SensorType, MaxChannels> = Vec::new();
+
+        for sensor in self.sensors.iter() {
+            for kind in sensor.kinds() {
+                if !kinds.contains(kind) && kinds.push(*kind).is_err() {
+                    break;
+                }
+            }
+        }
+
+        if kinds.contains(&SensorType::Temperature) && kinds.contains(&SensorType::Humidity) {
+            for kind in DERIVED_KINDS.iter() {
+                if kinds.push(*kind).is_err() {
+                    break;
+                }
+            }
+        }
+
+        kinds
+    }
+
+    // 全センサから測定値を集める
+    // 測定中のセンサは、そのセンサが前回読めた値を保つ（欠測にはしない）
+    // 読んだ直後に校正を当て、温度の補正はフィルタの前に行う
+    // 派生チャネルはフィルタを通した値から求める
+    pub fn sample(&mut self, timestamp: u32) -> Measurement {
+        let mut measurement = Measurement::new(timestamp);
+
+        for (i, sensor) in self.sensors.iter_mut().enumerate() {
+            let mut reading = Measurement::new(timestamp);
+            sensor.read(&mut reading);
+
+            let error = reading.error;
+            let reading = match error {
+                Some(SensorError::NotReady) => None,
+                _ => Some(reading)
+            };
+
+            measurement.is_updated |= reading.is_some();
+            if measurement.error.is_none() {
+                measurement.error = error;
+            }
+            if let Some(reading) = self.held.update(i, reading) {
+                measurement.merge(reading);
+            }
+        }
+        measurement.calibrate(&self.calibration);
+        if let Some(compensator) = self.compensator.as_mut() {
+            measurement.compensate(compensator);
+        }
+        for (kind, filter) in self.filters.iter_mut() {
+            measurement.filter(*kind, filter);
+        }
+        measurement.derive();
+
+        measurement
+    }
+}
+
+impl EnvironmentalSensor for SCD30<I2cHandle> {
+    fn kinds(&self) -> &'static [SensorType] {
+        &[SensorType::Temperature, SensorType::Humidity, SensorType::Co2Concentration]
+    }
+
+    fn read(&mut self, measurement: &mut Measurement) {
+        match self.is_available() {
+            Ok(true) => {},
+            Ok(false) => {
+                measurement.set_missing(self.kinds(), SensorError::NotReady);
+                return;
+            },
+            Err(_) => {
+                measurement.set_missing(self.kinds(), SensorError::ReadFailure);
+                return;
+            }
+        }
+
+        match self.get_value() {
+            Ok((co2, _, _)) if co2 < Ppm(100.0) => {
+                // なぜかまともなデータが取れないときは無視
+                measurement.set_missing(self.kinds(), SensorError::Implausible);
+            },
+            Ok((co2, tmp, hum)) => {
+                measurement.set_source_temperature(TemperatureSource::Scd30, tmp);
+                measurement.set(tmp);
+                measurement.set(hum);
+                measurement.set(co2);
+            },
+            Err(_) => measurement.set_missing(self.kinds(), SensorError::ReadFailure)
+        }
+    }
+}
+
+impl EnvironmentalSensor for BM1383AGLV<I2cHandle> {
+    fn kinds(&self) -> &'static [SensorType] {
+        &[SensorType::Temperature, SensorType::AtmPressure]
+    }
+
+    fn read(&mut self, measurement: &mut Measurement) {
+        match self.get_value() {
+            Ok((tmp, atm)) => {
+                measurement.set_source_temperature(TemperatureSource::Bm1383aglv, tmp);
+                measurement.set(tmp);
+                measurement.set(atm);
+            },
+            Err(ErrorBM1383AGLV::NoData) => measurement.set_missing(self.kinds(), SensorError::NotReady),
+            Err(_) => measurement.set_missing(self.kinds(), SensorError::ReadFailure)
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! air change rate estimation for wio_umwelt_monitor
+
+use heapless::consts::*;
+use heapless::Vec;
+
+// 減衰の始まりとみなす、直前の最大値からの低下[ppm]
+const START_DROP: f32 = 50.0;
+// 外気との差がこれ以上あるときだけ減衰を調べる[ppm]
+const MIN_START_EXCESS: f32 = 150.0;
+// 外気との差がこれを下回ったら減衰の終わりとする（対数が不安定になるため）[ppm]
+const MIN_EXCESS: f32 = 50.0;
+// 途中の最小値からこれ以上上がった値が続いたら減衰の終わりとする[ppm]
+// 短いスパイクで区切らないように、続いた回数も見る
+const RISE_TOLERANCE: f32 = 30.0;
+const MAX_RISES: u8 = 3;
+// 採用する減衰の最短と最長の期間[s]
+const MIN_DURATION: u32 = 15 * 60;
+const MAX_DURATION: u32 = 4 * 3600;
+// 採用するのに必要な点の数と、外気との差の減り方（始めと終わりの比）
+const MIN_POINTS: u32 = 10;
+const MIN_DECAY_RATIO: f32 = 1.5;
+
+// 推定の確からしさ
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High
+}
+
+// 1回の減衰から求めた換気回数
+#[derive(Debug, Copy, Clone)]
+pub struct AchEstimate {
+    pub end_timestamp: u32,
+    pub duration: u32,
+    // 換気回数[回/h]
+    pub ach: f32,
+    // 指数関数への当てはまり（決定係数）
+    pub r_squared: f32,
+    pub start_ppm: f32,
+    pub end_ppm: f32,
+    pub confidence: Confidence
+}
+
+// 減衰中の対数を取った外気との差への直線の当てはめ
+struct DecayFit {
+    start_timestamp: u32,
+    last_timestamp: u32,
+    start_ppm: f32,
+    last_ppm: f32,
+    min_ppm: f32,
+    rises: u8,
+    n: u32,
+    sum_t: f32,
+    sum_y: f32,
+    sum_tt: f32,
+    sum_ty: f32,
+    sum_yy: f32
+}
+
+// CO2濃度の減衰（人がいなくなった、窓を開けた）から換気回数を推定する
+// 減衰中は室内での発生が無いものとし、外気の濃度baselineに向かって指数関数的に下がるとみなす
+//   C(t) - baseline = (C(0) - baseline) * exp(-ACH * t)
+pub struct AchEstimator {
+    baseline: f32,
+    peak: Option<f32>,
+    fit: Option<DecayFit>,
+    estimates: Vec<AchEstimate, U8>,
+    last_timestamp: u32
+}
+
+impl Confidence {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high"
+        }
+    }
+}
+
+impl DecayFit {
+    fn new(timestamp: u32, ppm: f32) -> DecayFit {
+        DecayFit {
+            start_timestamp: timestamp,
+            last_timestamp: timestamp,
+            start_ppm: ppm,
+            last_ppm: ppm,
+            min_ppm: ppm,
+            rises: 0,
+            n: 0,
+            sum_t: 0.0,
+            sum_y: 0.0,
+            sum_tt: 0.0,
+            sum_ty: 0.0,
+            sum_yy: 0.0
+        }
+    }
+
+    // tは開始からの時間[h]、yは外気との差の対数（開始時との差にして桁落ちを防ぐ）
+    fn add(&mut self, timestamp: u32, ppm: f32, baseline: f32) {
+        let t = timestamp.wrapping_sub(self.start_timestamp) as f32 / 3600.0;
+        let y = libm::logf((ppm - baseline) / (self.start_ppm - baseline));
+
+        self.n += 1;
+        self.sum_t += t;
+        self.sum_y += y;
+        self.sum_tt += t * t;
+        self.sum_ty += t * y;
+        self.sum_yy += y * y;
+
+        self.last_timestamp = timestamp;
+        self.last_ppm = ppm;
+        self.min_ppm = self.min_ppm.min(ppm);
+    }
+
+    fn duration(&self) -> u32 {
+        self.last_timestamp.wrapping_sub(self.start_timestamp)
+    }
+
+    // 最小二乗法で傾きと決定係数を求める
+    fn finish(&self, baseline: f32) -> Option<AchEstimate> {
+        let duration = self.duration();
+        if self.n < MIN_POINTS || duration < MIN_DURATION {
+            return None;
+        }
+
+        let decay_ratio = (self.start_ppm - baseline) / (self.last_ppm - baseline);
+        if decay_ratio < MIN_DECAY_RATIO {
+            return None;
+        }
+
+        let n = self.n as f32;
+        let stt = n * self.sum_tt - self.sum_t * self.sum_t;
+        let sty = n * self.sum_ty - self.sum_t * self.sum_y;
+        let syy = n * self.sum_yy - self.sum_y * self.sum_y;
+        if stt <= 0.0 || syy <= 0.0 {
+            return None;
+        }
+
+        let ach = -sty / stt;
+        if ach <= 0.0 {
+            return None;
+        }
+        let r_squared = sty * sty / (stt * syy);
+
+        let confidence = if 0.95 <= r_squared && 30 * 60 <= duration && 2.0 <= decay_ratio {
+            Confidence::High
+        }
+        else if 0.85 <= r_squared && 20 * 60 <= duration {
+            Confidence::Medium
+        }
+        else {
+            Confidence::Low
+        };
+
+        Some(AchEstimate {
+            end_timestamp: self.last_timestamp,
+            duration,
+            ach,
+            r_squared,
+            start_ppm: self.start_ppm,
+            end_ppm: self.last_ppm,
+            confidence
+        })
+    }
+}
+
+impl AchEstimator {
+    // baselineは外気のCO2濃度[ppm]
+    pub fn new(baseline: f32) -> AchEstimator {
+        AchEstimator {
+            baseline,
+            peak: None,
+            fit: None,
+            estimates: Vec::new(),
+            last_timestamp: 0
+        }
+    }
+
+    pub fn baseline(&self) -> f32 {
+        self.baseline
+    }
+
+    // 減衰を調べている最中なら、その開始からの時間[s]
+    pub fn decaying_for(&self) -> Option<u32> {
+        self.fit.as_ref().map(|fit| fit.duration())
+    }
+
+    pub fn last_timestamp(&self) -> u32 {
+        self.last_timestamp
+    }
+
+    // 最近の推定結果（新しい順）
+    pub fn estimates(&self) -> impl Iterator<Item = &AchEstimate> {
+        self.estimates.iter().rev()
+    }
+
+    pub fn latest(&self) -> Option<&AchEstimate> {
+        self.estimates.last()
+    }
+
+    // CO2濃度を1つ加える（欠測はNone）
+    // 減衰が終わって換気回数が求まったらそれを返す
+    pub fn update(&mut self, timestamp: u32, co2: Option<f32>) -> Option<AchEstimate> {
+        self.last_timestamp = timestamp;
+
+        let ppm = match co2 {
+            Some(ppm) => ppm,
+            None => {
+                // 欠測をはさむと当てはめが崩れるので、そこで区切る
+                self.peak = None;
+                return self.finish();
+            }
+        };
+
+        let excess = ppm - self.baseline;
+
+        if let Some(fit) = self.fit.as_mut() {
+            // 上がった値は当てはめに使わない
+            if fit.min_ppm + RISE_TOLERANCE < ppm {
+                fit.rises += 1;
+            }
+            else {
+                fit.rises = 0;
+            }
+
+            let is_over = excess < MIN_EXCESS
+                || MAX_RISES <= fit.rises
+                || MAX_DURATION < timestamp.wrapping_sub(fit.start_timestamp);
+
+            if !is_over {
+                if fit.rises == 0 {
+                    fit.add(timestamp, ppm, self.baseline);
+                }
+                return None;
+            }
+
+            self.peak = Some(ppm);
+            return self.finish();
+        }
+
+        match self.peak {
+            Some(peak) if ppm <= peak - START_DROP && MIN_START_EXCESS <= peak - self.baseline => {
+                // 最大値の時点から当てはめたいが、最大値の時刻は持たないので低下を確認した時点から始める
+                let mut fit = DecayFit::new(timestamp, ppm);
+                fit.add(timestamp, ppm, self.baseline);
+                self.fit = Some(fit);
+                self.peak = None;
+            },
+            Some(peak) if ppm <= peak => {},
+            _ => self.peak = Some(ppm)
+        }
+
+        None
+    }
+
+    fn finish(&mut self) -> Option<AchEstimate> {
+        let estimate = self.fit.take()?.finish(self.baseline)?;
+
+        // いっぱいなら一番古いものを捨てる
+        if self.estimates.len() == self.estimates.capacity() {
+            self.estimates.rotate_left(1);
+            self.estimates.pop();
+        }
+        self.estimates.push(estimate).ok();
+
+        Some(estimate)
+    }
+}
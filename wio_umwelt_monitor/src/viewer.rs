@@ -7,41 +7,79 @@ use wio_terminal as wio;
 use eg::{fonts::*, pixelcolor::*, prelude::*, primitives::*, style::*};
 use core::fmt::Write;
 use heapless::consts::*;
-use heapless::String;
+use heapless::{String, Vec};
+
+use crate::analysis::Analysis;
+use crate::annotation::*;
+use crate::calibration::*;
+use crate::clock::Clock;
+use crate::dryness::Dryness;
+use crate::events::*;
+use crate::exposure::*;
+use crate::forecast::*;
+use crate::heat::HeatRisk;
+use crate::infection::*;
+use crate::mold::*;
+use crate::scanner::{DetectedDevices, DeviceKind};
+use crate::sensor::*;
+use crate::ventilation::*;
+use crate::weather::*;
+use history::*;
+use units::Hectopascal;
 
 
 // Defined constant values
-pub const INVALID_DAT_NUM: f32 = 999.9;
 pub const WINDOW_WIDTH: usize = 320;
+// 測定値を24時間分保持する（1チャネル約20KB）
+pub const HISTORY_CAPACITY: usize = 24 * 3600 / crate::SENSING_INTERVAL as usize;
+const MAX_MEASURED_HISTORIES: usize = 4;
+// 派生チャネルは集計の段で長期間を保持し、測定値そのものは表示幅の分だけ保持する（1チャネル約8KB）
+// 測定値と同じく24時間分を持つと6チャネルで約83KB増えてRAMに収まらない
+// 4時間と24時間のグラフは1分と10分ごとの集計から描くので、測定値のチャネルより粗くなる
+const MAX_DERIVED_HISTORIES: usize = 6;
+// 日ごとの集計は締めた日を31日分保持する（集計中の日は別）
+pub const DAILY_CAPACITY: usize = 31;
+
+// グラフに表示する期間[s]（0は測定値をそのまま表示する）
+const GRAPH_SPANS: [u32; 4] = [0, 4 * 3600, 24 * 3600, 7 * 24 * 3600];
+const GRAPH_SPAN_LABELS: [&str; 4] = ["1h", "4h", "24h", "7d"];
+const ROW_MAX_PITCH: i32 = 50;
+const COLOR_INACTIVE: Rgb565 = Rgb565::new(0x10, 0x20, 0x10);
+const COLOR_ANNOTATION: Rgb565 = Rgb565::YELLOW;
 //pub const WINDOW_HEIGHT: usize = 240; // unused variable
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum SensorType {
-    Temperature,
-    Humidity,
-    Co2Concentration,
-    AtmPressure
+// 同じ容量の履歴をチャネルの種類と対応付けて持つ
+struct HistoryBank<const N: usize, const M: usize> {
+    kinds: [Option<SensorType>; M],
+    histories: [TieredHistory<N, WINDOW_WIDTH>; M],
+    daily: [DailyHistory<DAILY_CAPACITY>; M]
 }
 
-pub struct DataHistory {
-    dat: [f32; WINDOW_WIDTH +1],
-    max: f32,
-    min: f32
+// 全チャネルの履歴（大きいのでstaticに置く）
+// 数値と列挙型だけでできているので、ゼロで埋めた領域に置いてresetを呼べば使える
+pub struct DataSet {
+    measured: HistoryBank<HISTORY_CAPACITY, MAX_MEASURED_HISTORIES>,
+    derived: HistoryBank<WINDOW_WIDTH, MAX_DERIVED_HISTORIES>,
+    interval: u32,
+    clock: Clock,
+    last_timestamp: Option<u32>,
+    // ボタンで付けた印（古い順）
+    annotations: Vec<Annotation, U32>
 }
 
-pub struct DataSet {
-    tmp: DataHistory,
-    hum: DataHistory,
-    atm: DataHistory,
-    co2: DataHistory
+// 測定値のチャネルと派生チャネルで履歴の容量が違うので、どちらの問い合わせ結果も扱えるようにする
+pub enum ChannelQuery<'a> {
+    Measured(Query<'a, HISTORY_CAPACITY, WINDOW_WIDTH>),
+    Derived(Query<'a, WINDOW_WIDTH, WINDOW_WIDTH>)
 }
 
 pub struct NumberPrintElement {
-    var: f32,
+    var: Option<f32>,
     x_r: i32,
     y: i32,
     recent: i32,
-    last: i32
+    last: i32,
+    color: Rgb565
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -49,88 +87,261 @@ pub struct Coordinates {
     title_x: i32,
     unit_x: i32,
     num_x_r: i32,
-    tmp_y: i32,
-    hum_y: i32,
-    co2_y: i32,
-    atm_y: i32,
+    rows_top_y: i32,
+    rows_bottom_y: i32,
     graph_y: i32,
     graph_height: i32
 }
 
+// 画面の種類
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Screen {
+    Main,
+    Ventilation,
+    Exposure,
+    Summary,
+    Calibration,
+    Help
+}
+
+// 1チャネル分の表示行
+pub struct ChannelRow {
+    kind: SensorType,
+    unit: Unit,
+    y: i32,
+    num: NumberPrintElement
+}
+
 pub struct Viewer {
     pos: Coordinates,
+    screen: Screen,
     mode: SensorType,
-    num_tmp: NumberPrintElement,
-    num_hum: NumberPrintElement,
-    num_co2: NumberPrintElement,
-    num_atm: NumberPrintElement,
-    history: DataSet
+    span: usize,
+    heat_alert_level: HeatRisk,
+    co2_threshold: f32,
+    co2_outlook: Option<Co2Outlook>,
+    occupancy: Option<u32>,
+    infection_risk: Option<(i32, RiskGrade)>,
+    altitude: f32,
+    storm_drop: f32,
+    pressure_outlook: Option<PressureOutlook>,
+    mold_status: Option<(MoldRisk, i32, bool, bool)>,
+    // 解析で見つけた出来事（グラフに印を付ける）
+    events: Vec<Event, U16>,
+    // 印の種類を選んでいる間は選んでいる位置
+    annotating: Option<usize>,
+    // 日ごとの集計の画面で表示している日（0が今日）
+    summary_age: u32,
+    // 校正の画面で表示する校正と選んでいる項目
+    calibration: CalibrationTable,
+    calibration_status: CalibrationStatus,
+    calibration_cursor: usize,
+    humidify_alert: bool,
+    humidify_status: Option<i32>,
+    // チャネルごとにフィルタが棄却した値の数
+    rejected: Vec<(SensorType, u32), MaxChannels>,
+    kinds: Vec<SensorType, MaxChannels>,
+    rows: Vec<ChannelRow, MaxChannels>,
+    history: &'static mut DataSet
 }
 
-impl DataHistory {
-    pub fn new() -> DataHistory {
-        DataHistory {
-            dat : [INVALID_DAT_NUM; WINDOW_WIDTH +1],
-            max : 0.0,
-            min : 0.0
-        }
+impl Screen {
+    const ALL: [Screen; 6] = [Screen::Main, Screen::Ventilation, Screen::Exposure, Screen::Summary, Screen::Calibration, Screen::Help];
+
+    fn next(&self) -> Screen {
+        let i = Screen::ALL.iter().position(|screen| screen == self).unwrap_or(0);
+        Screen::ALL[(i + 1) % Screen::ALL.len()]
+    }
+
+    fn prev(&self) -> Screen {
+        let i = Screen::ALL.iter().position(|screen| screen == self).unwrap_or(0);
+        Screen::ALL[(i + Screen::ALL.len() - 1) % Screen::ALL.len()]
+    }
+}
+
+impl<const N: usize, const M: usize> HistoryBank<N, M> {
+    fn clear(&mut self) {
+        self.kinds = [None; M];
     }
 
-    pub fn set_new_data(&mut self, new_data: f32) {
+    fn add(&mut self, sensor: SensorType, interval: u32) {
+        if self.find(sensor).is_some() {
+            return;
+        }
+
+        if let Some(i) = self.kinds.iter().position(|kind| kind.is_none()) {
+            self.kinds[i] = Some(sensor);
+            self.histories[i].reset(WINDOW_WIDTH, interval, get_encoding(sensor));
+            self.daily[i].clear();
+        }
+    }
 
-        self.max = new_data;
-        self.min = new_data;
-        self.dat[WINDOW_WIDTH] = new_data;
+    fn set_measurement(&mut self, timestamp: u32, clock: &Clock, measurement: Option<&Measurement>) {
+        let day = clock.day(timestamp);
+        let time_of_day = clock.time_of_day(timestamp);
 
-        for i in 0..WINDOW_WIDTH {
-            self.dat[i] = self.dat[i+1];
-            if INVALID_DAT_NUM != self.dat[i] {
-                if self.max < self.dat[i] {
-                    self.max = self.dat[i];
-                }
-                if self.dat[i] < self.min {
-                    self.min = self.dat[i];
-                }
+        for ((kind, history), daily) in self.kinds.iter().zip(self.histories.iter_mut()).zip(self.daily.iter_mut()) {
+            if let Some(kind) = kind {
+                let value = measurement.and_then(|measurement| measurement.get(*kind));
+                history.set_new_data(timestamp, value);
+                daily.set_new_data(day, time_of_day, value);
             }
         }
     }
 
-    pub fn get_rate(&self, itr: usize) -> f32 {
+    fn find(&self, sensor: SensorType) -> Option<&TieredHistory<N, WINDOW_WIDTH>> {
+        self.kinds.iter()
+            .position(|kind| *kind == Some(sensor))
+            .map(|i| &self.histories[i])
+    }
+
+    fn find_daily(&self, sensor: SensorType) -> Option<&DailyHistory<DAILY_CAPACITY>> {
+        self.kinds.iter()
+            .position(|kind| *kind == Some(sensor))
+            .map(|i| &self.daily[i])
+    }
+}
+
+impl DataSet {
+    // 履歴を消して測定間隔を設定し直す
+    // 大きな構造体を作らずに済むように、その場で初期化する
+    pub fn reset(&mut self, interval: u32) {
+        self.interval = interval;
+        self.clock = Clock::new(0);
+        self.clear();
+    }
+
+    // 日ごとの集計の日付の区切りに使う時計
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    pub fn clear(&mut self) {
+        self.measured.clear();
+        self.derived.clear();
+        self.last_timestamp = None;
+        self.annotations.clear();
+    }
+
+    // 印を追加する（いっぱいなら一番古いものを捨てる）
+    pub fn add_annotation(&mut self, annotation: Annotation) {
+        if self.annotations.len() == self.annotations.capacity() {
+            self.annotations.rotate_left(1);
+            self.annotations.pop();
+        }
+        self.annotations.push(annotation).ok();
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
 
-        if (self.dat[itr] != INVALID_DAT_NUM) && (0.0 < self.max - self.min) {
-            (self.dat[itr] - self.min) / (self.max - self.min)
+    // 履歴を持つチャネルを追加する
+    pub fn add_channel(&mut self, sensor: SensorType) {
+        if sensor.is_measured() {
+            self.measured.add(sensor, self.interval);
         }
         else {
-            0.0
+            self.derived.add(sensor, self.interval);
         }
     }
-}
 
-impl DataSet {
-    pub fn new() ->DataSet {
-        DataSet {
-            tmp: DataHistory::new(),
-            hum: DataHistory::new(),
-            atm: DataHistory::new(),
-            co2: DataHistory::new()
+    // 1回分の測定結果を追加する
+    // 測定できなかった周期があれば、時間軸がずれないようにその分を欠測として埋める
+    pub fn set_measurement(&mut self, measurement: &Measurement) {
+        if let Some(last) = self.last_timestamp {
+            let elapsed = measurement.timestamp.wrapping_sub(last);
+            let skipped = ((elapsed + self.interval / 2) / self.interval).saturating_sub(1);
+
+            for i in 1..=skipped.min(HISTORY_CAPACITY as u32) {
+                let timestamp = last.wrapping_add(i * self.interval);
+                self.measured.set_measurement(timestamp, &self.clock, None);
+                self.derived.set_measurement(timestamp, &self.clock, None);
+            }
+        }
+        self.last_timestamp = Some(measurement.timestamp);
+
+        self.measured.set_measurement(measurement.timestamp, &self.clock, Some(measurement));
+        self.derived.set_measurement(measurement.timestamp, &self.clock, Some(measurement));
+    }
+
+    pub fn get_latest(&self, sensor: SensorType) -> Option<f32> {
+        match self.measured.find(sensor) {
+            Some(history) => history.raw().get_latest(),
+            None => self.derived.find(sensor).and_then(|history| history.raw().get_latest())
+        }
+    }
+
+    // 期間spanを幅widthで描くための集計値を問い合わせる
+    pub fn query(&self, sensor: SensorType, span: u32, width: usize) -> Option<ChannelQuery<'_>> {
+        match self.measured.find(sensor) {
+            Some(history) => Some(ChannelQuery::Measured(history.query(span, width))),
+            None => self.derived.find(sensor).map(|history| ChannelQuery::Derived(history.query(span, width)))
+        }
+    }
+
+    // 期間spanのグラフを描けるだけの履歴を持っているかどうか
+    pub fn covers(&self, sensor: SensorType, span: u32) -> bool {
+        match self.measured.find(sensor) {
+            Some(history) => history.covers(span),
+            None => self.derived.find(sensor).is_some_and(|history| history.covers(span))
+        }
+    }
+
+    // 直近span秒の測定値から求めた傾向（傾きは測定間隔あたり）
+    pub fn trend(&self, sensor: SensorType, span: u32) -> Option<Trend> {
+        self.measured.find(sensor).and_then(|history| history.raw().trend((span / self.interval) as usize))
+    }
+
+    // span秒前からの変化（両端はそれぞれaverage秒分の測定値の平均）
+    pub fn change(&self, sensor: SensorType, span: u32, average: u32) -> Option<f32> {
+        let history = self.measured.find(sensor)?.raw();
+        let count = (average / self.interval) as usize;
+        let age = (span / self.interval) as usize;
+
+        Some(history.mean(0, count)? - history.mean(age, count)?)
+    }
+
+    // 最新の測定値の時刻
+    pub fn last_timestamp(&self) -> Option<u32> {
+        self.last_timestamp
+    }
+
+    // 最新の測定値の日付（まだ測定値が無ければNone）
+    pub fn today(&self) -> Option<u32> {
+        self.last_timestamp.map(|timestamp| self.clock.day(timestamp))
+    }
+
+    // 日付dayの集計
+    pub fn daily(&self, sensor: SensorType, day: u32) -> Option<DailySummary> {
+        match self.measured.find_daily(sensor) {
+            Some(daily) => daily.find(day),
+            None => self.derived.find_daily(sensor).and_then(|daily| daily.find(day))
         }
     }
 
-    pub fn set_new_data(&mut self, sensor: SensorType, value: f32) {
-        match sensor {
-            SensorType::Temperature => self.tmp.set_new_data(value),
-            SensorType::Humidity => self.hum.set_new_data(value),
-            SensorType::Co2Concentration => self.co2.set_new_data(value),
-            SensorType::AtmPressure => self.atm.set_new_data(value)
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    // 測定値をそのまま表示するときの期間
+    pub fn raw_span(&self) -> u32 {
+        self.interval * WINDOW_WIDTH as u32
+    }
+}
+
+impl<'a> ChannelQuery<'a> {
+    pub fn get(&self, column: usize) -> Bucket {
+        match self {
+            ChannelQuery::Measured(query) => query.get(column),
+            ChannelQuery::Derived(query) => query.get(column)
         }
     }
 
-    pub fn get_rate(&self, sensor: SensorType, itr: usize) -> f32 {
-        match sensor {
-            SensorType::Temperature => self.tmp.get_rate(itr),
-            SensorType::Humidity => self.hum.get_rate(itr),
-            SensorType::Co2Concentration => self.co2.get_rate(itr),
-            SensorType::AtmPressure => self.atm.get_rate(itr)
+    pub fn range(&self) -> Option<(f32, f32)> {
+        match self {
+            ChannelQuery::Measured(query) => query.range(),
+            ChannelQuery::Derived(query) => query.range()
         }
     }
 }
@@ -138,36 +349,54 @@ impl DataSet {
 impl NumberPrintElement {
     pub fn new(x_right: i32, y: i32) -> NumberPrintElement {
         NumberPrintElement {
-            var: INVALID_DAT_NUM,
+            var: None,
             x_r: x_right,
-            y: y,
+            y,
             recent: 0,
-            last: 0
+            last: 0,
+            color: Rgb565::BLACK
         }
     }
 
-    pub fn print(&mut self, display: &mut wio::LCD, value: f32, color: Rgb565) {
+    pub fn print(&mut self, display: &mut wio::LCD, value: UnitValue, color: Rgb565) {
+        let value = value.value();
 
         // 表示範囲を前回と比較するための小数点以下第2位を四捨五入して10倍した値
         self.recent = (10.0 * value + 0.5) as i32;
 
-        // 値に変化があったときだけ表示を更新する
-        if self.recent != self.last {
+        // 値か色に変化があったときだけ表示を更新する
+        if self.recent != self.last || color != self.color {
 
             self.print_sub(display, Rgb565::BLACK);
-            self.var = value;
+            self.var = Some(value);
+            self.color = color;
             self.print_sub(display, color);
 
             self.last = self.recent;
         }
     }
 
+    // 表示を消して、次のprint()で必ず描き直すようにする
+    pub fn clear(&mut self, display: &mut wio::LCD) {
+        self.print_sub(display, Rgb565::BLACK);
+        self.var = None;
+        self.color = Rgb565::BLACK;
+    }
+
+    // 値が得られなかったときは前回の値を灰色で表示する
+    pub fn print_missing(&mut self, display: &mut wio::LCD) {
+        if self.color != COLOR_INACTIVE {
+            self.color = COLOR_INACTIVE;
+            self.print_sub(display, COLOR_INACTIVE);
+        }
+    }
+
     //  右詰め小数点以下1桁で数値を表示
     fn print_sub(&mut self, display: &mut wio::LCD, color: Rgb565) {
 
-        if self.var != INVALID_DAT_NUM {
+        if let Some(var) = self.var {
             let mut textbuf = String::<U32>::new();
-            write!(&mut textbuf, "{:.1}", self.var).unwrap();
+            write!(&mut textbuf, "{:.1}", var).unwrap();
 
             let x_l = self.x_r - (textbuf.len() as i32) * 25;
 
@@ -179,16 +408,21 @@ impl NumberPrintElement {
     }
 }
 
+impl ChannelRow {
+    // 行の単位に換算した値
+    fn display_value(&self, value: f32) -> UnitValue {
+        UnitValue::new(self.kind, value, self.unit)
+    }
+}
+
 impl Coordinates {
-    pub fn new(x_title: i32, x_unit: i32, x_num_r: i32, y_tmp: i32, y_hum: i32, y_co2: i32, y_atm: i32, y_graph: i32, height_graph: i32)-> Coordinates {
+    pub fn new(x_title: i32, x_unit: i32, x_num_r: i32, y_rows_top: i32, y_rows_bottom: i32, y_graph: i32, height_graph: i32)-> Coordinates {
         Coordinates {
             title_x: x_title,
             unit_x: x_unit,
             num_x_r: x_num_r,
-            tmp_y: y_tmp,
-            hum_y: y_hum,
-            co2_y: y_co2,
-            atm_y: y_atm,
+            rows_top_y: y_rows_top,
+            rows_bottom_y: y_rows_bottom,
             graph_y: y_graph,
             graph_height: height_graph,
         }
@@ -196,171 +430,1489 @@ impl Coordinates {
 }
 
 impl Viewer {
-    pub fn new(cordinates: Coordinates)-> Viewer {
+    pub fn new(cordinates: Coordinates, history: &'static mut DataSet)-> Viewer {
         Viewer {
             pos: cordinates,
+            screen: Screen::Main,
             mode: SensorType::Co2Concentration,
-            num_tmp: NumberPrintElement::new(cordinates.num_x_r, cordinates.tmp_y),
-            num_hum: NumberPrintElement::new(cordinates.num_x_r, cordinates.hum_y),
-            num_co2: NumberPrintElement::new(cordinates.num_x_r, cordinates.co2_y),
-            num_atm: NumberPrintElement::new(cordinates.num_x_r, cordinates.atm_y),
-            history: DataSet::new()
+            span: 0,
+            heat_alert_level: HeatRisk::SevereWarning,
+            co2_threshold: 1000.0,
+            co2_outlook: None,
+            occupancy: None,
+            infection_risk: None,
+            altitude: 0.0,
+            storm_drop: 3.0,
+            pressure_outlook: None,
+            mold_status: None,
+            annotating: None,
+            summary_age: 0,
+            calibration: CalibrationTable::new(),
+            calibration_status: CalibrationStatus::Saved,
+            calibration_cursor: 0,
+            events: Vec::new(),
+            humidify_alert: false,
+            humidify_status: None,
+            rejected: Vec::new(),
+            kinds: Vec::new(),
+            rows: Vec::new(),
+            history
         }
     }
 
-    pub fn update(&mut self, display: &mut wio::LCD, tmp: f32, hum: f32, co2: f32, atm: f32) {
-        self.history.set_new_data(SensorType::Temperature, tmp);
-        self.history.set_new_data(SensorType::Humidity, hum);
-        self.history.set_new_data(SensorType::Co2Concentration, co2);
-        self.history.set_new_data(SensorType::AtmPressure, atm);
+    // 履歴を持つチャネル（グラフで選べるチャネル）を設定し、
+    // そのうちrowsに含まれるものにrowsの順で数値の行を割り付ける
+    pub fn set_channels(&mut self, kinds: &[SensorType], rows: &[SensorType]) {
+        self.kinds.clear();
+        self.rows.clear();
+        self.history.clear();
 
-        let color_co2 = if 1000.0 <= co2 {Rgb565::RED} else {get_color(SensorType::Co2Concentration)};
-        self.num_tmp.print(display, tmp, get_color(SensorType::Temperature));
-        self.num_hum.print(display, hum, get_color(SensorType::Humidity));
-        self.num_co2.print(display, co2, color_co2);
-        self.num_atm.print(display, atm, get_color(SensorType::AtmPressure));
-        self.write_graph(display);
-    }
+        for kind in kinds.iter() {
+            if self.kinds.push(*kind).is_err() {
+                break;
+            }
+            self.history.add_channel(*kind);
+        }
 
-    pub fn next_mode (&mut self, display: &mut wio::LCD) {
-        self.mode = match self.mode {
-            SensorType::Temperature => SensorType::Humidity,
-            SensorType::Humidity => SensorType::Co2Concentration,
-            SensorType::Co2Concentration => SensorType::AtmPressure,
-            SensorType::AtmPressure => SensorType::Temperature
-        };
+        if self.kinds.is_empty() {
+            return;
+        }
 
-        self.write_graph(display);
+        let count = rows.iter().filter(|kind| self.kinds.contains(kind)).count().max(1);
+        let pitch = ROW_MAX_PITCH.min((self.pos.rows_bottom_y - self.pos.rows_top_y) / count as i32);
+
+        for kind in rows.iter().filter(|kind| kinds.contains(kind)) {
+            let y = self.pos.rows_top_y + pitch * self.rows.len() as i32;
+            let row = ChannelRow {
+                kind: *kind,
+                unit: kind.unit(),
+                y,
+                num: NumberPrintElement::new(self.pos.num_x_r, y)
+            };
+
+            if self.rows.push(row).is_err() {
+                break;
+            }
+        }
+
+        if !self.kinds.contains(&self.mode) {
+            self.mode = self.kinds[0];
+        }
     }
 
-    // 数値以外の変動しない表示を描画
-    pub fn print_labels(&mut self, display: &mut wio::LCD) {
+    // 表示する単位を変更する（同じ物理量の単位の間でのみ変更できる）
+    pub fn set_display_unit(&mut self, unit: Unit) {
+        for row in self.rows.iter_mut() {
+            if row.kind.unit() == unit.base() {
+                row.unit = unit;
+            }
+        }
+    }
 
-        Text::new("Temp.", Point::new(self.pos.title_x, self.pos.tmp_y))
-            .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // 暑さ指数がこの温度基準域以上になったら警報とする
+    pub fn set_heat_alert_level(&mut self, level: HeatRisk) {
+        self.heat_alert_level = level;
+    }
 
-        Text::new(".", Point::new(self.pos.unit_x-5, self.pos.tmp_y-25))
-            .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // CO2濃度がこの値に達するまでの時間を予測する
+    pub fn set_co2_threshold(&mut self, threshold: f32) {
+        self.co2_threshold = threshold;
+    }
 
-        Text::new("C", Point::new(self.pos.unit_x+8, self.pos.tmp_y))
-            .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // 海面気圧に換算するための設置場所の標高[m]
+    pub fn set_altitude(&mut self, altitude: f32) {
+        self.altitude = altitude;
+    }
 
-        Text::new("Humid.", Point::new(self.pos.title_x, self.pos.hum_y))
-            .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // 3時間でこの値[hPa]以上気圧が下がったら警報とする
+    pub fn set_storm_alert_drop(&mut self, drop: f32) {
+        self.storm_drop = drop;
+    }
 
-        Text::new("%", Point::new(self.pos.unit_x, self.pos.hum_y))
-            .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
-        Text::new("CO2", Point::new(self.pos.title_x, self.pos.co2_y))
-            .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // 日ごとの集計の日付の区切りに使う時計
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.history.set_clock(clock);
+    }
 
-        Text::new("ppm", Point::new(self.pos.unit_x, self.pos.co2_y))
-            .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // 乾燥しているときに加湿を促すかどうか
+    pub fn set_humidify_alert(&mut self, enabled: bool) {
+        self.humidify_alert = enabled;
+    }
 
-        Text::new("Atm.", Point::new(self.pos.title_x, self.pos.atm_y))
-            .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // 加湿を促す警報が出ているかどうか
+    pub fn is_humidify_alert(&self) -> bool {
+        self.humidify_alert && self.history.get_latest(SensorType::AbsoluteHumidity)
+            .is_some_and(|absolute| Dryness::from_absolute(absolute) == Dryness::Dry)
+    }
 
-        Text::new("hPa", Point::new(self.pos.unit_x, self.pos.atm_y))
-            .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
-            .draw(display)
-            .unwrap();
+    // 気圧が急に下がっているかどうか
+    pub fn is_storm_alert(&self) -> bool {
+        self.pressure_outlook().is_some_and(|outlook| outlook.storm)
     }
 
-    // グラフエリアの描画
-    fn write_graph(&mut self, display: &mut wio::LCD) {
+    // 最新の暑さ指数が警報の温度基準域に達しているかどうか
+    pub fn is_heat_alert(&self) -> bool {
+        match self.history.get_latest(SensorType::Wbgt) {
+            Some(wbgt) => self.heat_alert_level <= HeatRisk::from_wbgt(wbgt),
+            None => false
+        }
+    }
 
-        let y_bottom: i32 = self.pos.graph_y + self.pos.graph_height;
-        let color = get_color( self.mode);
+    // チャネルごとに独立して表示を更新する（値が無いチャネルだけ灰色にする）
+    pub fn update(&mut self, display: &mut wio::LCD, measurement: &Measurement, analysis: &Analysis) {
+        self.history.set_measurement(measurement);
 
-        let style =  PrimitiveStyleBuilder::new()
-            .fill_color(color)
-            .build();
+        self.rejected.clear();
+        for kind in self.kinds.iter() {
+            self.rejected.push((*kind, measurement.rejected(*kind))).ok();
+        }
 
-        let style_black = PrimitiveStyleBuilder::new()
-            .fill_color(Rgb565::BLACK)
-            .build();
+        self.events.clear();
+        self.events.extend_from_slice(analysis.events.events()).ok();
 
-        let style_red = PrimitiveStyleBuilder::new()
-            .fill_color(Rgb565::RED)
-            .build();
+        // 印の種類を選んでいる間は描かない（閉じるときに描き直す）
+        if self.annotating.is_some() {
+            return;
+        }
 
-        for i in 0..320 {
-            let mut value = (self.pos.graph_height as f32 * self.history.get_rate(self.mode, i as usize)) as i32;
+        match self.screen {
+            Screen::Main => self.update_main(display, measurement, analysis),
+            Screen::Ventilation => self.print_ventilation(display, &analysis.ventilation),
+            Screen::Exposure => self.print_exposure(display, &analysis.exposure),
+            // 集計中の今日を表示しているときだけ描き直す
+            Screen::Summary if self.summary_age == 0 => self.print_summary(display),
+            Screen::Summary => (),
+            Screen::Calibration => (),
+            Screen::Help => self.print_infection_status(display, &analysis.infection)
+        }
+    }
 
-            if value < 0 {
-                value = 0;
-            }
+    pub fn next_screen(&mut self, display: &mut wio::LCD, analysis: &Analysis) {
+        self.screen = self.screen.next();
+        self.show_screen(display, analysis);
+    }
 
-            let bar_reset =
-                Rectangle::new(Point::new(i, self.pos.graph_y), Point::new(i, y_bottom-value))
-                    .into_styled(style_black);
-            bar_reset.draw(display).unwrap();
+    pub fn prev_screen(&mut self, display: &mut wio::LCD, analysis: &Analysis) {
+        self.screen = self.screen.prev();
+        self.show_screen(display, analysis);
+    }
 
-            if 0 < value {
-                let bar = if (self.mode == SensorType::Co2Concentration) && (1000.0 <= self.history.co2.dat[i as usize]) {
-                    Rectangle::new(Point::new(i, y_bottom-value), Point::new(i, y_bottom))
-                        .into_styled(style_red)
+    // 画面を消して、今の画面を最初から描く
+    fn show_screen(&mut self, display: &mut wio::LCD, analysis: &Analysis) {
+        let background = Rectangle::new(Point::new(0, 0), Point::new(319, 239))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        background.draw(display).unwrap();
+
+        match self.screen {
+            Screen::Main => {
+                self.print_labels(display);
+
+                // 最新の値で数値を描き直す
+                for row in self.rows.iter_mut() {
+                    row.num.clear(display);
+                    if let Some(value) = self.history.get_latest(row.kind) {
+                        row.num.print(display, row.display_value(value), get_row_color(row.kind, value, self.history));
+                    }
                 }
-                else {
-                    Rectangle::new(Point::new(i, y_bottom-value), Point::new(i, y_bottom))
-                        .into_styled(style)
-                };
 
-                bar.draw(display).unwrap();
+                self.co2_outlook = None;
+                self.print_co2_outlook(display);
+                self.occupancy = None;
+                self.print_occupancy(display);
+                self.infection_risk = None;
+                self.print_infection_risk(display, &analysis.infection);
+                self.pressure_outlook = None;
+                self.print_pressure_outlook(display);
+                self.mold_status = None;
+                self.print_mold_risk(display, &analysis.mold);
+                self.humidify_status = None;
+                self.print_humidify(display);
+                self.write_graph(display);
+            },
+            Screen::Ventilation => {
+                Text::new("Ventilation", Point::new(self.pos.title_x, 5))
+                    .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                    .draw(display)
+                    .unwrap();
+
+                self.print_ventilation(display, &analysis.ventilation);
+            },
+            Screen::Exposure => {
+                Text::new("CO2 exposure", Point::new(self.pos.title_x, 5))
+                    .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                    .draw(display)
+                    .unwrap();
+
+                self.print_exposure_legend(display, analysis.exposure.settings());
+                self.print_exposure(display, &analysis.exposure);
+            },
+            Screen::Summary => {
+                Text::new("Daily summary", Point::new(self.pos.title_x, 5))
+                    .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                    .draw(display)
+                    .unwrap();
+
+                self.print_summary(display);
+            },
+            Screen::Calibration => {
+                Text::new("Calibration", Point::new(self.pos.title_x, 5))
+                    .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                    .draw(display)
+                    .unwrap();
+
+                self.print_calibration(display);
+            },
+            Screen::Help => {
+                Text::new("Help", Point::new(self.pos.title_x, 5))
+                    .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                    .draw(display)
+                    .unwrap();
+
+                self.print_help(display, analysis);
             }
         }
     }
-}
 
-// 画面の初期化
-pub fn print_initializing(display: &mut wio::LCD, is_initialized: bool) {
+    fn update_main(&mut self, display: &mut wio::LCD, measurement: &Measurement, analysis: &Analysis) {
+        for row in self.rows.iter_mut() {
+            match measurement.get(row.kind) {
+                Some(value) => row.num.print(display, row.display_value(value), get_row_color(row.kind, value, self.history)),
+                None => row.num.print_missing(display)
+            }
+        }
 
-    let style = PrimitiveStyleBuilder::new()
-        .fill_color(Rgb565::BLACK)
-        .build();
+        self.print_co2_outlook(display);
+        self.print_occupancy(display);
+        self.print_infection_risk(display, &analysis.infection);
+        self.print_pressure_outlook(display);
+        self.print_mold_risk(display, &analysis.mold);
+        self.print_humidify(display);
+        self.write_graph(display);
+    }
 
-    // LCDを黒色で塗りつぶす
-    let background =
-        Rectangle::new(Point::new(0, 0), Point::new(319, 239))
-            .into_styled(style);
-    background.draw(display).unwrap();
+    pub fn is_annotating(&self) -> bool {
+        self.annotating.is_some()
+    }
 
-    if is_initialized {
-        // 初期化中表示
-        Text::new("Initializing...", Point::new(70, 210))
-            .into_styled(TextStyle::new(Font12x16, Rgb565::GREEN))
+    // 印の種類を選ぶ一覧を開く
+    pub fn begin_annotation(&mut self, display: &mut wio::LCD) {
+        if self.annotating.is_none() {
+            self.annotating = Some(0);
+            self.print_annotation_menu(display);
+        }
+    }
+
+    // 一覧で選ぶ位置を動かす
+    pub fn next_category(&mut self, display: &mut wio::LCD) {
+        if let Some(i) = self.annotating {
+            self.annotating = Some((i + 1) % AnnotationCategory::ALL.len());
+            self.print_annotation_menu(display);
+        }
+    }
+
+    pub fn prev_category(&mut self, display: &mut wio::LCD) {
+        if let Some(i) = self.annotating {
+            self.annotating = Some((i + AnnotationCategory::ALL.len() - 1) % AnnotationCategory::ALL.len());
+            self.print_annotation_menu(display);
+        }
+    }
+
+    // 選んでいる種類の印を付けて一覧を閉じる
+    pub fn confirm_annotation(&mut self, display: &mut wio::LCD, analysis: &Analysis, timestamp: u32) -> Option<Annotation> {
+        let i = self.annotating.take()?;
+        let annotation = Annotation { timestamp, category: AnnotationCategory::ALL[i] };
+
+        self.history.add_annotation(annotation);
+        self.show_screen(display, analysis);
+
+        Some(annotation)
+    }
+
+    // 印を付けずに一覧を閉じる
+    pub fn cancel_annotation(&mut self, display: &mut wio::LCD, analysis: &Analysis) {
+        if self.annotating.take().is_some() {
+            self.show_screen(display, analysis);
+        }
+    }
+
+    // 印の種類の一覧を今の画面に重ねて描く
+    fn print_annotation_menu(&self, display: &mut wio::LCD) {
+        let selected = match self.annotating {
+            Some(selected) => selected,
+            None => return
+        };
+        let (x, y) = (70, 50);
+        let bottom = y + 24 + 14 * AnnotationCategory::ALL.len() as i32;
+
+        Rectangle::new(Point::new(x, y), Point::new(x + 180, bottom))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).stroke_color(Rgb565::WHITE).stroke_width(1).build())
             .draw(display)
             .unwrap();
-    }
-    else {
-        Text::new("initialization failure", Point::new(30, 210))
-            .into_styled(TextStyle::new(Font12x16, Rgb565::RED))
+
+        Text::new("press: mark / left: cancel", Point::new(x + 6, y + 6))
+            .into_styled(TextStyle::new(Font6x8, Rgb565::CYAN))
             .draw(display)
             .unwrap();
+
+        for (i, category) in AnnotationCategory::ALL.iter().enumerate() {
+            let (foreground, background) = if i == selected {
+                (Rgb565::BLACK, Rgb565::WHITE)
+            }
+            else {
+                (Rgb565::WHITE, Rgb565::BLACK)
+            };
+            let row_y = y + 22 + 14 * i as i32;
+
+            Rectangle::new(Point::new(x + 4, row_y - 2), Point::new(x + 176, row_y + 9))
+                .into_styled(PrimitiveStyleBuilder::new().fill_color(background).build())
+                .draw(display)
+                .unwrap();
+            Text::new(category.label(), Point::new(x + 8, row_y))
+                .into_styled(TextStyle::new(Font6x8, foreground))
+                .draw(display)
+                .unwrap();
+        }
     }
-}
 
-// 各センサに対応した色
-pub fn get_color(sensor: SensorType) -> Rgb565 {
-    match sensor {
-        SensorType::Temperature => {Rgb565::MAGENTA},
-        SensorType::Humidity => {Rgb565::CYAN},
-        SensorType::Co2Concentration => {Rgb565::GREEN},
-        SensorType::AtmPressure => {Rgb565::new(0x1c, 0x28, 0x1f)}
+    // 校正を表示に反映する（校正の画面なら描き直す）
+    pub fn set_calibration(&mut self, display: &mut wio::LCD, table: &CalibrationTable, status: CalibrationStatus) {
+        self.calibration = *table;
+        self.calibration_status = status;
+
+        if self.screen == Screen::Calibration && self.annotating.is_none() {
+            self.print_calibration(display);
+        }
+    }
+
+    // 校正の画面で選んでいるチャネルと項目（校正の画面でなければNone）
+    pub fn calibration_field(&self) -> Option<(SensorType, CalibrationField)> {
+        if self.screen != Screen::Calibration || self.annotating.is_some() {
+            return None;
+        }
+
+        let kind = self.calibrated_kinds().nth(self.calibration_cursor / 2)?;
+        let field = match self.calibration_cursor % 2 {
+            0 => CalibrationField::Offset,
+            _ => CalibrationField::Gain
+        };
+
+        Some((kind, field))
+    }
+
+    // 校正の画面で次の項目を選ぶ
+    pub fn next_calibration_field(&mut self, display: &mut wio::LCD) {
+        if self.screen != Screen::Calibration {
+            return;
+        }
+
+        let count = 2 * self.calibrated_kinds().count();
+        if 0 < count {
+            self.calibration_cursor = (self.calibration_cursor + 1) % count;
+        }
+        self.print_calibration(display);
+    }
+
+    // 校正できるチャネルのうち測っているもの
+    fn calibrated_kinds(&self) -> impl Iterator<Item = SensorType> + '_ {
+        CALIBRATED_KINDS.iter().copied().filter(move |kind| self.kinds.contains(kind))
+    }
+
+    // 日ごとの集計の画面で1日前を表示する
+    pub fn prev_day(&mut self, display: &mut wio::LCD) {
+        if self.screen != Screen::Summary || DAILY_CAPACITY as u32 <= self.summary_age {
+            return;
+        }
+
+        self.summary_age += 1;
+        self.print_summary(display);
+    }
+
+    // 日ごとの集計の画面で1日後を表示する
+    pub fn next_day(&mut self, display: &mut wio::LCD) {
+        if self.screen != Screen::Summary || self.summary_age == 0 {
+            return;
+        }
+
+        self.summary_age -= 1;
+        self.print_summary(display);
+    }
+
+    // グラフに表示する期間を切り替える
+    pub fn next_span(&mut self, display: &mut wio::LCD) {
+        if self.screen != Screen::Main {
+            return;
+        }
+
+        // 表示中のチャネルの履歴で描けない期間は飛ばす（測定値をそのまま表示する期間は必ず描ける）
+        self.span = (1..=GRAPH_SPANS.len())
+            .map(|step| (self.span + step) % GRAPH_SPANS.len())
+            .find(|span| self.history.covers(self.mode, self.graph_span(*span)))
+            .unwrap_or(0);
+
+        self.write_graph(display);
+    }
+
+    pub fn next_mode (&mut self, display: &mut wio::LCD) {
+        if self.screen != Screen::Main {
+            return;
+        }
+
+        if let Some(i) = self.kinds.iter().position(|kind| *kind == self.mode) {
+            self.mode = self.kinds[(i + 1) % self.kinds.len()];
+        }
+        if !self.history.covers(self.mode, self.graph_span(self.span)) {
+            self.span = 0;
+        }
+
+        self.write_graph(display);
+    }
+
+    // 数値以外の変動しない表示を描画
+    pub fn print_labels(&mut self, display: &mut wio::LCD) {
+        for row in self.rows.iter() {
+            Text::new(row.kind.title(), Point::new(self.pos.title_x, row.y))
+                .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                .draw(display)
+                .unwrap();
+
+            self.print_unit(display, row);
+        }
+    }
+
+    // 表示中のグラフのチャネルの単位を切り替える
+    pub fn next_unit(&mut self, display: &mut wio::LCD) {
+        if self.screen != Screen::Main {
+            return;
+        }
+
+        let mode = self.mode;
+        let latest = self.history.get_latest(mode);
+
+        if let Some(i) = self.rows.iter().position(|row| row.kind == mode) {
+            let next = self.rows[i].unit.next();
+            if next == self.rows[i].unit {
+                return;
+            }
+
+            // 単位と数値を消してから描き直す
+            let erase = Rectangle::new(Point::new(self.pos.unit_x-5, self.rows[i].y-4), Point::new(319, self.rows[i].y+31))
+                .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+            erase.draw(display).unwrap();
+            self.rows[i].num.clear(display);
+
+            self.rows[i].unit = next;
+            self.print_unit(display, &self.rows[i]);
+
+            if let Some(latest) = latest {
+                let row = &mut self.rows[i];
+                row.num.print(display, row.display_value(latest), get_row_color(mode, latest, self.history));
+            }
+        }
+    }
+
+    fn print_unit(&self, display: &mut wio::LCD, row: &ChannelRow) {
+        let unit = row.unit;
+
+        if unit.base() == Unit::Celsius {
+            // 度記号の代わりに小さく上げたピリオドを描く
+            Text::new(".", Point::new(self.pos.unit_x-5, row.y-25))
+                .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
+                .draw(display)
+                .unwrap();
+
+            Text::new(unit.label(), Point::new(self.pos.unit_x+8, row.y))
+                .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
+                .draw(display)
+                .unwrap();
+        }
+        else if unit.label().len() <= 3 {
+            Text::new(unit.label(), Point::new(self.pos.unit_x, row.y))
+                .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
+                .draw(display)
+                .unwrap();
+        }
+        else {
+            // 長い単位は小さい文字で下寄せにする
+            Text::new(unit.label(), Point::new(self.pos.unit_x, row.y+16))
+                .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // GRAPH_SPANSのi番目の期間[s]
+    fn graph_span(&self, i: usize) -> u32 {
+        match GRAPH_SPANS[i] {
+            0 => self.history.raw_span(),
+            span => span
+        }
+    }
+
+    // グラフエリアの描画
+    fn write_graph(&mut self, display: &mut wio::LCD) {
+
+        let y_bottom: i32 = self.pos.graph_y + self.pos.graph_height;
+        let color = get_color( self.mode);
+
+        let style =  PrimitiveStyleBuilder::new()
+            .fill_color(color)
+            .build();
+
+        let style_black = PrimitiveStyleBuilder::new()
+            .fill_color(Rgb565::BLACK)
+            .build();
+
+        let style_red = PrimitiveStyleBuilder::new()
+            .fill_color(Rgb565::RED)
+            .build();
+
+        let span = self.graph_span(self.span);
+
+        let query = match self.history.query(self.mode, span, WINDOW_WIDTH) {
+            Some(query) => query,
+            None => return
+        };
+        let range = query.range();
+
+        for i in 0..WINDOW_WIDTH as i32 {
+            let bucket = query.get(i as usize);
+
+            // 欠測の期間は何も描かない（有効な値は最小値でも1ドット描く）
+            let value = match range {
+                _ if bucket.is_empty() => 0,
+                Some((min, max)) if 0.0 < max - min => {
+                    let rate = (bucket.mean - min) / (max - min);
+                    ((self.pos.graph_height as f32 * rate) as i32).clamp(1, self.pos.graph_height)
+                },
+                _ => 1
+            };
+
+            let bar_reset =
+                Rectangle::new(Point::new(i, self.pos.graph_y), Point::new(i, y_bottom-value))
+                    .into_styled(style_black);
+            bar_reset.draw(display).unwrap();
+
+            if 0 < value {
+                let bar = if is_alert(self.mode, bucket.mean) {
+                    Rectangle::new(Point::new(i, y_bottom-value), Point::new(i, y_bottom))
+                        .into_styled(style_red)
+                }
+                else {
+                    Rectangle::new(Point::new(i, y_bottom-value), Point::new(i, y_bottom))
+                        .into_styled(style)
+                };
+
+                bar.draw(display).unwrap();
+            }
+        }
+
+        self.print_event_markers(display, span);
+
+        // 表示しているチャネルと期間
+        Text::new(self.mode.title(), Point::new(0, self.pos.graph_y))
+            .into_styled(TextStyle::new(Font6x8, color))
+            .draw(display)
+            .unwrap();
+
+        Text::new(GRAPH_SPAN_LABELS[self.span], Point::new(WINDOW_WIDTH as i32 - 26, self.pos.graph_y))
+            .into_styled(TextStyle::new(Font6x8, Rgb565::WHITE))
+            .draw(display)
+            .unwrap();
+
+        // フィルタが棄却した値の数
+        if let Some((_, rejected)) = self.rejected.iter().find(|(kind, rejected)| *kind == self.mode && 0 < *rejected) {
+            let mut textbuf = String::<U32>::new();
+            write!(&mut textbuf, "rej {}", rejected).unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(WINDOW_WIDTH as i32 - 32 - textbuf.len() as i32 * 6, self.pos.graph_y))
+                .into_styled(TextStyle::new(Font6x8, Rgb565::YELLOW))
+                .draw(display)
+                .unwrap();
+        }
+
+        self.print_heat_risk(display);
+    }
+
+    // 出来事とボタンで付けた印の時刻の列に点線と種類の1文字を描く（右端が最新の測定値）
+    fn print_event_markers(&self, display: &mut wio::LCD, span: u32) {
+        let events = self.events.iter()
+            .map(|event| (event.timestamp, event.kind.marker(), get_event_color(event.kind)));
+        let annotations = self.history.annotations().iter()
+            .map(|annotation| (annotation.timestamp, annotation.category.marker(), COLOR_ANNOTATION));
+
+        for (timestamp, marker, color) in events.chain(annotations) {
+            self.print_marker(display, span, timestamp, marker, color);
+        }
+    }
+
+    fn print_marker(&self, display: &mut wio::LCD, span: u32, timestamp: u32, marker: &str, color: Rgb565) {
+        let now = match self.history.last_timestamp() {
+            Some(now) => now,
+            None => return
+        };
+
+        // 最新の測定値より後に付けた印は右端に描く
+        let age = now.saturating_sub(timestamp);
+        if span <= age {
+            return;
+        }
+
+        let x = WINDOW_WIDTH as i32 - 1 - (age as u64 * WINDOW_WIDTH as u64 / span as u64) as i32;
+        let y_bottom = self.pos.graph_y + self.pos.graph_height;
+
+        for y in (self.pos.graph_y + 9..=y_bottom).step_by(3) {
+            Pixel(Point::new(x, y), color).draw(display).unwrap();
+        }
+
+        Text::new(marker, Point::new((x - 2).clamp(0, WINDOW_WIDTH as i32 - 6), self.pos.graph_y + 9))
+            .into_styled(TextStyle::new(Font6x8, color))
+            .draw(display)
+            .unwrap();
+    }
+
+    // CO2濃度の行の下に、しきい値に達するまでの予測を表示する
+    fn print_co2_outlook(&mut self, display: &mut wio::LCD) {
+        let y = match self.rows.iter().find(|row| row.kind == SensorType::Co2Concentration) {
+            Some(row) => row.y + 35,
+            None => return
+        };
+
+        let outlook = self.history.trend(SensorType::Co2Concentration, FORECAST_WINDOW)
+            .map(|trend| Co2Outlook::from_trend(&trend, self.history.interval(), self.co2_threshold));
+
+        // 変化があったときだけ描き直す
+        if outlook == self.co2_outlook {
+            return;
+        }
+        self.co2_outlook = outlook;
+
+        let erase = Rectangle::new(Point::new(self.pos.title_x, y), Point::new(self.pos.title_x + 150, y + 7))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        let mut textbuf = String::<U32>::new();
+        let color = match outlook {
+            Some(Co2Outlook::Above) => {
+                write!(&mut textbuf, "above {:.0} ppm", self.co2_threshold).unwrap();
+                Rgb565::RED
+            },
+            Some(Co2Outlook::Rising { minutes }) => {
+                write!(&mut textbuf, "{:.0} ppm in ~{} min", self.co2_threshold, minutes).unwrap();
+                Rgb565::YELLOW
+            },
+            Some(Co2Outlook::Steady) => {
+                write!(&mut textbuf, "steady").unwrap();
+                Rgb565::WHITE
+            },
+            Some(Co2Outlook::Falling) => {
+                write!(&mut textbuf, "falling").unwrap();
+                Rgb565::WHITE
+            },
+            None => return
+        };
+
+        Text::new(textbuf.as_str(), Point::new(self.pos.title_x, y))
+            .into_styled(TextStyle::new(Font6x8, color))
+            .draw(display)
+            .unwrap();
+    }
+
+    // 3時間の気圧変化とそれから求めた予報（3時間分の履歴が溜まるまではNone）
+    fn pressure_outlook(&self) -> Option<PressureOutlook> {
+        let pressure = self.history.get_latest(SensorType::AtmPressure)?;
+        let change = self.history.change(SensorType::AtmPressure, TENDENCY_WINDOW, TENDENCY_AVERAGE)?;
+
+        // 表示する0.1hPa単位に丸める（丸めた値が変わったときだけ描き直す）
+        let change = libm::roundf(change * 10.0) / 10.0;
+        let sea_level = Hectopascal(pressure).to_sea_level(self.altitude);
+
+        Some(PressureOutlook::new(sea_level.0, change, self.storm_drop))
+    }
+
+    // 気圧の行の見出しの下に3時間の変化を、行の下に傾向と予報を表示する
+    fn print_pressure_outlook(&mut self, display: &mut wio::LCD) {
+        let y = match self.rows.iter().find(|row| row.kind == SensorType::AtmPressure) {
+            Some(row) => row.y,
+            None => return
+        };
+
+        let outlook = self.pressure_outlook();
+        if outlook == self.pressure_outlook {
+            return;
+        }
+        self.pressure_outlook = outlook;
+
+        let style_black = PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build();
+        Rectangle::new(Point::new(self.pos.title_x, y + 20), Point::new(self.pos.title_x + 66, y + 27))
+            .into_styled(style_black)
+            .draw(display)
+            .unwrap();
+        Rectangle::new(Point::new(self.pos.title_x, y + 35), Point::new(319, y + 42))
+            .into_styled(style_black)
+            .draw(display)
+            .unwrap();
+
+        if let Some(outlook) = outlook {
+            let color = if outlook.storm {
+                Rgb565::RED
+            }
+            else {
+                get_weather_color(outlook.forecast.class())
+            };
+
+            let mut textbuf = String::<U64>::new();
+            write!(&mut textbuf, "{:+.1}hPa/3h", outlook.change).unwrap();
+            Text::new(textbuf.as_str(), Point::new(self.pos.title_x, y + 20))
+                .into_styled(TextStyle::new(Font6x8, color))
+                .draw(display)
+                .unwrap();
+
+            textbuf.clear();
+            write!(&mut textbuf, "{}: {}", outlook.tendency.label(), outlook.forecast.text()).unwrap();
+            Text::new(textbuf.as_str(), Point::new(self.pos.title_x, y + 35))
+                .into_styled(TextStyle::new(Font6x8, color))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // 湿度の行の見出しの下にカビのスコアを、行の下に結露の注意を表示する
+    fn print_mold_risk(&mut self, display: &mut wio::LCD, mold: &MoldTracker) {
+        let y = match self.rows.iter().find(|row| row.kind == SensorType::Humidity) {
+            Some(row) => row.y,
+            None => return
+        };
+
+        // スコアは0.1時間単位で変わったときだけ描き直す
+        let status = (mold.risk(), (mold.score() * 10.0) as i32, mold.is_wet(), mold.is_condensing());
+        if Some(status) == self.mold_status {
+            return;
+        }
+        self.mold_status = Some(status);
+
+        let style_black = PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build();
+        Rectangle::new(Point::new(self.pos.title_x, y + 20), Point::new(self.pos.title_x + 66, y + 27))
+            .into_styled(style_black)
+            .draw(display)
+            .unwrap();
+        Rectangle::new(Point::new(self.pos.title_x, y + 35), Point::new(self.pos.title_x + 150, y + 42))
+            .into_styled(style_black)
+            .draw(display)
+            .unwrap();
+
+        // 生育条件にあってスコアが増えている間は「+」を付ける
+        let (risk, _, is_wet, is_condensing) = status;
+        if risk != MoldRisk::None {
+            let mut textbuf = String::<U32>::new();
+            write!(&mut textbuf, "mold {:.1}h{}", mold.score(), if is_wet { "+" } else { "" }).unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(self.pos.title_x, y + 20))
+                .into_styled(TextStyle::new(Font6x8, get_mold_risk_color(risk)))
+                .draw(display)
+                .unwrap();
+        }
+
+        if is_condensing {
+            Text::new("condensation risk", Point::new(self.pos.title_x, y + 35))
+                .into_styled(TextStyle::new(Font6x8, Rgb565::CYAN))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // 湿度の行の下の右側に、乾燥しているときは加湿を促す表示をする
+    fn print_humidify(&mut self, display: &mut wio::LCD) {
+        let y = match self.rows.iter().find(|row| row.kind == SensorType::Humidity) {
+            Some(row) => row.y + 35,
+            None => return
+        };
+
+        // 絶対湿度は0.1g/m3単位で変わったときだけ描き直す
+        let status = if self.is_humidify_alert() {
+            self.history.get_latest(SensorType::AbsoluteHumidity).map(|absolute| (absolute * 10.0 + 0.5) as i32)
+        }
+        else {
+            None
+        };
+        if status == self.humidify_status {
+            return;
+        }
+        self.humidify_status = status;
+
+        let x = self.pos.title_x + 160;
+        let erase = Rectangle::new(Point::new(x, y), Point::new(319, y + 7))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        if let Some(absolute) = status {
+            let mut textbuf = String::<U32>::new();
+            write!(&mut textbuf, "humidify ({:.1} g/m3)", absolute as f32 / 10.0).unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(x, y))
+                .into_styled(TextStyle::new(Font6x8, get_dryness_color(Dryness::Dry)))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // CO2濃度の行の下の右側に、推定した在室人数を表示する
+    fn print_occupancy(&mut self, display: &mut wio::LCD) {
+        let y = match self.rows.iter().find(|row| row.kind == SensorType::Co2Concentration) {
+            Some(row) => row.y + 35,
+            None => return
+        };
+
+        // 整数に丸めたものが変わったときだけ描き直す
+        let occupancy = self.history.get_latest(SensorType::Occupancy).map(|people| (people + 0.5) as u32);
+        if occupancy == self.occupancy {
+            return;
+        }
+        self.occupancy = occupancy;
+
+        let x = self.pos.title_x + 160;
+        let erase = Rectangle::new(Point::new(x, y), Point::new(319, y + 7))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        if let Some(people) = occupancy {
+            let mut textbuf = String::<U32>::new();
+            write!(&mut textbuf, "~{} {}", people, if people == 1 { "person" } else { "people" }).unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(x, y))
+                .into_styled(TextStyle::new(Font6x8, get_color(SensorType::Occupancy)))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // CO2濃度の行の見出しの下に、再呼吸率と感染リスクの目安を表示する
+    fn print_infection_risk(&mut self, display: &mut wio::LCD, model: &InfectionModel) {
+        let y = match self.rows.iter().find(|row| row.kind == SensorType::Co2Concentration) {
+            Some(row) => row.y + 20,
+            None => return
+        };
+
+        // 再呼吸率（0.1%単位）か段階が変わったときだけ描き直す
+        let risk = self.history.get_latest(SensorType::Co2Concentration).map(|co2| model.evaluate(co2));
+        let key = risk.map(|risk| ((risk.rebreathed * 1000.0 + 0.5) as i32, risk.grade));
+        if key == self.infection_risk {
+            return;
+        }
+        self.infection_risk = key;
+
+        let erase = Rectangle::new(Point::new(self.pos.title_x, y), Point::new(self.pos.title_x + 66, y + 7))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        if let Some(risk) = risk {
+            let mut textbuf = String::<U32>::new();
+            write!(&mut textbuf, "{:.1}% {}", risk.rebreathed * 100.0, risk.grade.label()).unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(self.pos.title_x, y))
+                .into_styled(TextStyle::new(Font6x8, get_infection_risk_color(risk.grade)))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // チャネルごとの校正を一覧して、選んでいる項目を反転して描く
+    fn print_calibration(&self, display: &mut wio::LCD) {
+        let x = self.pos.title_x;
+        let small = TextStyle::new(Font6x8, Rgb565::WHITE);
+
+        let erase = Rectangle::new(Point::new(0, 24), Point::new(319, 239))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        Text::new("Press: select  Left/Right: -/+  middle key: save", Point::new(x, 28))
+            .into_styled(TextStyle::new(Font6x8, Rgb565::CYAN))
+            .draw(display)
+            .unwrap();
+
+        Text::new("          offset      gain", Point::new(x, 44))
+            .into_styled(small)
+            .draw(display)
+            .unwrap();
+
+        let selected = self.calibration_field();
+        let mut textbuf = String::<U64>::new();
+        let mut y = 58;
+
+        for kind in self.calibrated_kinds() {
+            let calibration = self.calibration.get(kind).unwrap_or(Calibration::IDENTITY);
+
+            Text::new(kind.title(), Point::new(x, y))
+                .into_styled(small)
+                .draw(display)
+                .unwrap();
+
+            for (field, cell_x) in [(CalibrationField::Offset, x + 54), (CalibrationField::Gain, x + 126)].iter() {
+                textbuf.clear();
+                match field {
+                    CalibrationField::Offset => write!(&mut textbuf, "{:>+9.2} {}", calibration.offset, kind.unit().label()),
+                    CalibrationField::Gain => write!(&mut textbuf, "{:>9.3}", calibration.gain)
+                }.unwrap();
+
+                let style = if selected == Some((kind, *field)) {
+                    TextStyleBuilder::new(Font6x8).text_color(Rgb565::BLACK).background_color(Rgb565::WHITE).build()
+                }
+                else {
+                    small
+                };
+                Text::new(textbuf.as_str(), Point::new(*cell_x, y))
+                    .into_styled(style)
+                    .draw(display)
+                    .unwrap();
+            }
+
+            y += 14;
+        }
+
+        let status_color = match self.calibration_status {
+            CalibrationStatus::Saved => Rgb565::GREEN,
+            CalibrationStatus::Edited => Rgb565::YELLOW,
+            CalibrationStatus::SaveFailed => Rgb565::RED
+        };
+        textbuf.clear();
+        write!(&mut textbuf, "status: {}", self.calibration_status.label()).unwrap();
+        Text::new(textbuf.as_str(), Point::new(x, y + 6))
+            .into_styled(TextStyle::new(Font6x8, status_color))
+            .draw(display)
+            .unwrap();
+
+        Text::new("value = reading x gain + offset", Point::new(x, y + 20))
+            .into_styled(small)
+            .draw(display)
+            .unwrap();
+    }
+
+    // 感染リスクの目安の前提を一覧する画面
+    fn print_help(&self, display: &mut wio::LCD, analysis: &Analysis) {
+        let x = self.pos.title_x;
+        let model = &analysis.infection;
+        let small = TextStyle::new(Font6x8, Rgb565::WHITE);
+
+        Text::new("Infection risk next to CO2: f% grade", Point::new(x, 44))
+            .into_styled(TextStyle::new(Font6x8, Rgb565::CYAN))
+            .draw(display)
+            .unwrap();
+
+        let mut textbuf = String::<U64>::new();
+        for line in 0..12 {
+            textbuf.clear();
+            match line {
+                0 => write!(&mut textbuf, "f: rebreathed fraction of room air"),
+                1 => write!(&mut textbuf, "   = (CO2 - outdoor) / {:.0} ppm", EXHALED_CO2),
+                2 => write!(&mut textbuf, "outdoor CO2: {:.0} ppm", model.baseline),
+                3 => write!(&mut textbuf, "activity: {} (emission x{:.1})", model.activity.label(), model.activity.emission_factor()),
+                4 => write!(&mut textbuf, "relative risk = f x emission / reference"),
+                5 => write!(&mut textbuf, "  reference: f {:.1}% {} (CO2 {:.0} ppm)",
+                    REFERENCE_REBREATHED * 100.0, REFERENCE_ACTIVITY.label(), model.baseline + REFERENCE_REBREATHED * EXHALED_CO2),
+                6 => write!(&mut textbuf, "  (Wells-Riley, low dose approx.)"),
+                7 => write!(&mut textbuf, "low < 0.5 <= mod < 1 <= high < 2 <= v.high"),
+                8 => write!(&mut textbuf, "assumes well mixed air, CO2 only from"),
+                9 => write!(&mut textbuf, "occupants, no masks or filtration."),
+                10 => write!(&mut textbuf, "Relative indicator only,"),
+                _ => write!(&mut textbuf, "not a medical assessment.")
+            }.unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(x, 60 + 14 * line))
+                .into_styled(small)
+                .draw(display)
+                .unwrap();
+        }
+
+        self.print_infection_status(display, model);
+    }
+
+    // ヘルプ画面の先頭に、今の再呼吸率と相対リスクを表示する
+    fn print_infection_status(&self, display: &mut wio::LCD, model: &InfectionModel) {
+        let x = self.pos.title_x;
+
+        let erase = Rectangle::new(Point::new(x, 28), Point::new(319, 35))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        let mut textbuf = String::<U64>::new();
+        let color = match self.history.get_latest(SensorType::Co2Concentration) {
+            Some(co2) => {
+                let risk = model.evaluate(co2);
+                write!(&mut textbuf, "now: f {:.1}%, relative risk x{:.1} ({})",
+                    risk.rebreathed * 100.0, risk.relative, risk.grade.label()).unwrap();
+                get_infection_risk_color(risk.grade)
+            },
+            None => {
+                write!(&mut textbuf, "now: no CO2 reading").unwrap();
+                COLOR_INACTIVE
+            }
+        };
+
+        Text::new(textbuf.as_str(), Point::new(x, 28))
+            .into_styled(TextStyle::new(Font6x8, color))
+            .draw(display)
+            .unwrap();
+    }
+
+    // 表示している日の全チャネルの最小値・最大値（とその時刻）・平均値の表
+    fn print_summary(&self, display: &mut wio::LCD) {
+        let x = self.pos.title_x;
+        let small = TextStyle::new(Font6x8, Rgb565::WHITE);
+
+        let erase = Rectangle::new(Point::new(0, 24), Point::new(319, 239))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        let mut textbuf = String::<U64>::new();
+        match self.summary_age {
+            0 => write!(&mut textbuf, "today"),
+            1 => write!(&mut textbuf, "yesterday"),
+            age => write!(&mut textbuf, "{} days ago", age)
+        }.unwrap();
+        write!(&mut textbuf, "  (Left/Right: older/newer)").unwrap();
+        Text::new(textbuf.as_str(), Point::new(x, 28))
+            .into_styled(TextStyle::new(Font6x8, Rgb565::CYAN))
+            .draw(display)
+            .unwrap();
+
+        Text::new("           min    at     max    at    mean", Point::new(x, 44))
+            .into_styled(small)
+            .draw(display)
+            .unwrap();
+
+        let day = match self.history.today() {
+            Some(today) if self.summary_age <= today => today - self.summary_age,
+            _ => return
+        };
+
+        for (i, kind) in (0..).zip(self.kinds.iter()) {
+            let y = 58 + 14 * i;
+
+            textbuf.clear();
+            write!(&mut textbuf, "{:<7}", kind.title()).unwrap();
+            match self.history.daily(*kind, day) {
+                Some(summary) => write!(&mut textbuf, "{:>7.1} {:02}:{:02} {:>7.1} {:02}:{:02} {:>7.1} {}",
+                    summary.min, summary.min_time / 60, summary.min_time % 60,
+                    summary.max, summary.max_time / 60, summary.max_time % 60,
+                    summary.mean, kind.unit().label()),
+                None => write!(&mut textbuf, "     --")
+            }.unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(x, y))
+                .into_styled(TextStyle::new(Font6x8, get_color(*kind)))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // CO2濃度の区分の凡例と集計する時間帯
+    fn print_exposure_legend(&self, display: &mut wio::LCD, settings: &ExposureSettings) {
+        let mut x = self.pos.title_x;
+        let mut textbuf = String::<U32>::new();
+
+        for band in 0..BAND_COUNT {
+            textbuf.clear();
+            match band {
+                0 => write!(&mut textbuf, "<{:.0}", settings.limits[0]),
+                _ if band == BAND_COUNT - 1 => write!(&mut textbuf, ">={:.0}", settings.limits[band - 1]),
+                _ => write!(&mut textbuf, "{:.0}-{:.0}", settings.limits[band - 1], settings.limits[band])
+            }.unwrap();
+
+            Text::new(textbuf.as_str(), Point::new(x, 28))
+                .into_styled(TextStyle::new(Font6x8, get_band_color(band)))
+                .draw(display)
+                .unwrap();
+            x += textbuf.len() as i32 * 6 + 8;
+        }
+
+        textbuf.clear();
+        write!(&mut textbuf, "ppm, {:02}-{:02}h", settings.work_hours.0, settings.work_hours.1).unwrap();
+        Text::new(textbuf.as_str(), Point::new(x, 28))
+            .into_styled(TextStyle::new(Font6x8, Rgb565::WHITE))
+            .draw(display)
+            .unwrap();
+    }
+
+    // 日ごとのCO2濃度の区分ごとの時間の割合を積み上げた棒で表示する（今日が一番上）
+    fn print_exposure(&self, display: &mut wio::LCD, exposure: &ExposureTracker) {
+        const BAR_X: i32 = 50;
+        const BAR_WIDTH: i32 = 230;
+
+        let style_black = PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build();
+        let mut textbuf = String::<U32>::new();
+
+        let days = core::iter::once(exposure.today()).chain(exposure.days().iter());
+        for (i, bands) in (0..).zip(days) {
+            let y = 44 + i * 24;
+
+            textbuf.clear();
+            match i {
+                0 => write!(&mut textbuf, "today"),
+                _ => write!(&mut textbuf, "-{}d", i)
+            }.unwrap();
+            Text::new(textbuf.as_str(), Point::new(self.pos.title_x, y + 3))
+                .into_styled(TextStyle::new(Font6x8, Rgb565::WHITE))
+                .draw(display)
+                .unwrap();
+
+            // 値が得られた時間に対する割合で塗り分ける（端数は最後の区分で埋める）
+            let total = bands.total();
+            let mut x = BAR_X;
+            if 0 < total {
+                for band in 0..BAND_COUNT {
+                    let right = if band == BAND_COUNT - 1 {
+                        BAR_X + BAR_WIDTH
+                    }
+                    else {
+                        let elapsed: u32 = bands.seconds[..=band].iter().sum();
+                        BAR_X + (BAR_WIDTH as u64 * elapsed as u64 / total as u64) as i32
+                    };
+
+                    if x < right {
+                        Rectangle::new(Point::new(x, y), Point::new(right - 1, y + 13))
+                            .into_styled(PrimitiveStyleBuilder::new().fill_color(get_band_color(band)).build())
+                            .draw(display)
+                            .unwrap();
+                    }
+                    x = right;
+                }
+            }
+            else {
+                Rectangle::new(Point::new(BAR_X, y), Point::new(BAR_X + BAR_WIDTH - 1, y + 13))
+                    .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).stroke_color(COLOR_INACTIVE).stroke_width(1).build())
+                    .draw(display)
+                    .unwrap();
+            }
+
+            // 一番低い区分の割合
+            Rectangle::new(Point::new(BAR_X + BAR_WIDTH + 4, y + 3), Point::new(319, y + 10))
+                .into_styled(style_black)
+                .draw(display)
+                .unwrap();
+            if let Some(fraction) = bands.fraction(0) {
+                textbuf.clear();
+                write!(&mut textbuf, "{:.0}%", fraction * 100.0).unwrap();
+                Text::new(textbuf.as_str(), Point::new(BAR_X + BAR_WIDTH + 6, y + 3))
+                    .into_styled(TextStyle::new(Font6x8, get_band_color(0)))
+                    .draw(display)
+                    .unwrap();
+            }
+        }
+    }
+
+    // 換気回数の推定結果の画面（タイトル以外を描き直す）
+    fn print_ventilation(&self, display: &mut wio::LCD, ventilation: &AchEstimator) {
+        let x = self.pos.title_x;
+        let now = ventilation.last_timestamp();
+        let small = TextStyle::new(Font6x8, Rgb565::WHITE);
+
+        let erase = Rectangle::new(Point::new(0, 24), Point::new(319, 239))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb565::BLACK).build());
+        erase.draw(display).unwrap();
+
+        let mut textbuf = String::<U64>::new();
+        write!(&mut textbuf, "air changes per hour (outdoor {:.0} ppm)", ventilation.baseline()).unwrap();
+        Text::new(textbuf.as_str(), Point::new(x, 28))
+            .into_styled(small)
+            .draw(display)
+            .unwrap();
+
+        match ventilation.latest() {
+            Some(estimate) => {
+                textbuf.clear();
+                write!(&mut textbuf, "{:.1}", estimate.ach).unwrap();
+                Text::new(textbuf.as_str(), Point::new(x, 44))
+                    .into_styled(TextStyle::new(Font24x32, Rgb565::WHITE))
+                    .draw(display)
+                    .unwrap();
+
+                let x_unit = x + textbuf.len() as i32 * 25 + 4;
+                Text::new("/h", Point::new(x_unit, 60))
+                    .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+                    .draw(display)
+                    .unwrap();
+
+                Text::new(estimate.confidence.label(), Point::new(x_unit + 40, 60))
+                    .into_styled(TextStyle::new(Font12x16, get_confidence_color(estimate.confidence)))
+                    .draw(display)
+                    .unwrap();
+
+                textbuf.clear();
+                write!(&mut textbuf, "R2 {:.2}  {} min  {:.0}->{:.0} ppm  ", estimate.r_squared, estimate.duration / 60, estimate.start_ppm, estimate.end_ppm).unwrap();
+                write_age(&mut textbuf, now.wrapping_sub(estimate.end_timestamp));
+                Text::new(textbuf.as_str(), Point::new(x, 84))
+                    .into_styled(small)
+                    .draw(display)
+                    .unwrap();
+            },
+            None => {
+                Text::new("no estimate yet", Point::new(x, 52))
+                    .into_styled(TextStyle::new(Font12x16, COLOR_INACTIVE))
+                    .draw(display)
+                    .unwrap();
+            }
+        }
+
+        textbuf.clear();
+        match ventilation.decaying_for() {
+            Some(duration) => write!(&mut textbuf, "CO2 decay in progress: {} min", duration / 60).unwrap(),
+            None => write!(&mut textbuf, "waiting for CO2 decay").unwrap()
+        }
+        Text::new(textbuf.as_str(), Point::new(x, 100))
+            .into_styled(TextStyle::new(Font6x8, Rgb565::CYAN))
+            .draw(display)
+            .unwrap();
+
+        // 最近の推定結果の一覧
+        for (i, estimate) in ventilation.estimates().enumerate() {
+            textbuf.clear();
+            write!(&mut textbuf, "{:5.1}/h  {:<6}  R2 {:.2}  {:3} min  ", estimate.ach, estimate.confidence.label(), estimate.r_squared, estimate.duration / 60).unwrap();
+            write_age(&mut textbuf, now.wrapping_sub(estimate.end_timestamp));
+
+            Text::new(textbuf.as_str(), Point::new(x, 120 + 14 * i as i32))
+                .into_styled(TextStyle::new(Font6x8, get_confidence_color(estimate.confidence)))
+                .draw(display)
+                .unwrap();
+        }
+    }
+
+    // 暑さ指数の温度基準域を色付きで表示する（警報中は赤枠で囲む）
+    fn print_heat_risk(&self, display: &mut wio::LCD) {
+        let wbgt = match self.history.get_latest(SensorType::Wbgt) {
+            Some(wbgt) => wbgt,
+            None => return
+        };
+        let risk = HeatRisk::from_wbgt(wbgt);
+
+        let mut textbuf = String::<U32>::new();
+        write!(&mut textbuf, "WBGT {:.1} {}", wbgt, risk.label()).unwrap();
+
+        let x = 110;
+        let width = textbuf.len() as i32 * 6 + 3;
+        let (background, foreground) = get_heat_risk_color(risk);
+
+        let border = if self.is_heat_alert() { Rgb565::RED } else { background };
+        let badge = Rectangle::new(Point::new(x - 2, self.pos.graph_y - 1), Point::new(x + width, self.pos.graph_y + 8))
+            .into_styled(PrimitiveStyleBuilder::new().fill_color(background).stroke_color(border).stroke_width(1).build());
+        badge.draw(display).unwrap();
+
+        Text::new(textbuf.as_str(), Point::new(x, self.pos.graph_y))
+            .into_styled(TextStyle::new(Font6x8, foreground))
+            .draw(display)
+            .unwrap();
+    }
+}
+
+// 画面の初期化
+pub fn print_initializing(display: &mut wio::LCD, is_initialized: bool) {
+
+    let style = PrimitiveStyleBuilder::new()
+        .fill_color(Rgb565::BLACK)
+        .build();
+
+    // LCDを黒色で塗りつぶす
+    let background =
+        Rectangle::new(Point::new(0, 0), Point::new(319, 239))
+            .into_styled(style);
+    background.draw(display).unwrap();
+
+    if is_initialized {
+        // 初期化中表示
+        Text::new("Initializing...", Point::new(70, 210))
+            .into_styled(TextStyle::new(Font12x16, Rgb565::GREEN))
+            .draw(display)
+            .unwrap();
+    }
+    else {
+        Text::new("initialization failure", Point::new(30, 210))
+            .into_styled(TextStyle::new(Font12x16, Rgb565::RED))
+            .draw(display)
+            .unwrap();
+    }
+}
+
+// 検出したI2Cデバイスの一覧表示
+pub fn print_detected_devices(display: &mut wio::LCD, devices: &DetectedDevices) {
+
+    let style = PrimitiveStyleBuilder::new()
+        .fill_color(Rgb565::BLACK)
+        .build();
+
+    // LCDを黒色で塗りつぶす
+    let background =
+        Rectangle::new(Point::new(0, 0), Point::new(319, 239))
+            .into_styled(style);
+    background.draw(display).unwrap();
+
+    Text::new("I2C devices", Point::new(10, 5))
+        .into_styled(TextStyle::new(Font12x16, Rgb565::WHITE))
+        .draw(display)
+        .unwrap();
+
+    let mut y = 30;
+    for device in devices.iter() {
+        let color = if device.kind == DeviceKind::Unknown {COLOR_INACTIVE} else {Rgb565::GREEN};
+
+        let mut textbuf = String::<U32>::new();
+        write!(&mut textbuf, "0x{:02X} {}", device.address, device.kind.name()).unwrap();
+
+        Text::new(textbuf.as_str(), Point::new(10, y))
+            .into_styled(TextStyle::new(Font12x16, color))
+            .draw(display)
+            .unwrap();
+
+        y += 20;
+    }
+
+    if y == 30 {
+        Text::new("no device found", Point::new(10, y))
+            .into_styled(TextStyle::new(Font12x16, Rgb565::RED))
+            .draw(display)
+            .unwrap();
+    }
+}
+
+// 各センサに対応した色
+pub fn get_color(sensor: SensorType) -> Rgb565 {
+    match sensor {
+        SensorType::Temperature => {Rgb565::MAGENTA},
+        SensorType::Humidity => {Rgb565::CYAN},
+        SensorType::Co2Concentration => {Rgb565::GREEN},
+        SensorType::AtmPressure => {Rgb565::new(0x1c, 0x28, 0x1f)},
+        SensorType::DewPoint => {Rgb565::new(0x0c, 0x30, 0x1f)},
+        SensorType::AbsoluteHumidity => {Rgb565::new(0x00, 0x3f, 0x14)},
+        SensorType::DiscomfortIndex => {Rgb565::YELLOW},
+        SensorType::HeatIndex => {Rgb565::new(0x1f, 0x20, 0x00)},
+        SensorType::Wbgt => {Rgb565::new(0x1f, 0x30, 0x00)},
+        SensorType::Occupancy => {Rgb565::new(0x18, 0x30, 0x1f)}
+    }
+}
+
+// 推定の確からしさの表示色
+pub fn get_confidence_color(confidence: Confidence) -> Rgb565 {
+    match confidence {
+        Confidence::High => Rgb565::GREEN,
+        Confidence::Medium => Rgb565::YELLOW,
+        Confidence::Low => Rgb565::RED
+    }
+}
+
+pub fn get_infection_risk_color(grade: RiskGrade) -> Rgb565 {
+    match grade {
+        RiskGrade::Low => Rgb565::GREEN,
+        RiskGrade::Moderate => Rgb565::YELLOW,
+        RiskGrade::High => Rgb565::new(31, 40, 0),
+        RiskGrade::VeryHigh => Rgb565::RED
+    }
+}
+
+// 経過時間を「12 min ago」のように書く
+fn write_age(textbuf: &mut String<U64>, seconds: u32) {
+    if seconds < 3600 {
+        write!(textbuf, "{} min ago", seconds / 60).ok();
+    }
+    else if seconds < 2 * 24 * 3600 {
+        write!(textbuf, "{} h ago", seconds / 3600).ok();
+    }
+    else {
+        write!(textbuf, "{} d ago", seconds / (24 * 3600)).ok();
+    }
+}
+
+pub fn get_event_color(kind: EventKind) -> Rgb565 {
+    match kind {
+        EventKind::WindowOpened => Rgb565::CYAN,
+        EventKind::DoorSlam => Rgb565::MAGENTA
+    }
+}
+
+// CO2濃度の区分の色（0が最も低い）
+pub fn get_band_color(band: usize) -> Rgb565 {
+    match band {
+        0 => Rgb565::GREEN,
+        1 => Rgb565::YELLOW,
+        2 => Rgb565::new(31, 40, 0),
+        _ => Rgb565::RED
+    }
+}
+
+pub fn get_mold_risk_color(risk: MoldRisk) -> Rgb565 {
+    match risk {
+        MoldRisk::None => Rgb565::WHITE,
+        MoldRisk::Elevated => Rgb565::YELLOW,
+        MoldRisk::Sustained => Rgb565::RED
+    }
+}
+
+pub fn get_weather_color(class: WeatherClass) -> Rgb565 {
+    match class {
+        WeatherClass::Fine => Rgb565::YELLOW,
+        WeatherClass::Changeable => Rgb565::WHITE,
+        WeatherClass::Rain => Rgb565::CYAN,
+        WeatherClass::Stormy => Rgb565::MAGENTA
+    }
+}
+
+// 暑さ指数の温度基準域の背景色と文字色
+pub fn get_heat_risk_color(risk: HeatRisk) -> (Rgb565, Rgb565) {
+    match risk {
+        HeatRisk::Safe => (Rgb565::BLUE, Rgb565::WHITE),
+        HeatRisk::Caution => (Rgb565::CYAN, Rgb565::BLACK),
+        HeatRisk::Warning => (Rgb565::YELLOW, Rgb565::BLACK),
+        HeatRisk::SevereWarning => (Rgb565::new(0x1f, 0x28, 0x00), Rgb565::BLACK),
+        HeatRisk::Danger => (Rgb565::RED, Rgb565::WHITE)
+    }
+}
+
+// 履歴に保持するときの符号化（表示と同じ刻み）
+// CO2濃度は1ppm刻みで0〜65534ppm、それ以外は0.1刻みで、気圧は-2276.7〜4276.7hPaの範囲を表せる
+pub fn get_encoding(sensor: SensorType) -> Encoding {
+    match sensor {
+        SensorType::Co2Concentration => Encoding::new(32767.0, 1.0),
+        SensorType::AtmPressure => Encoding::new(1000.0, 0.1),
+        _ => Encoding::new(0.0, 0.1)
+    }
+}
+
+// 注意が必要な値かどうか
+pub fn is_alert(sensor: SensorType, value: f32) -> bool {
+    match sensor {
+        SensorType::Co2Concentration => 1000.0 <= value,
+        // 不快指数80以上は全員が不快に感じる
+        SensorType::DiscomfortIndex => 80.0 <= value,
+        SensorType::AbsoluteHumidity => Dryness::from_absolute(value) == Dryness::Dry,
+        _ => false
+    }
+}
+
+// 値に応じた表示色
+pub fn get_value_color(sensor: SensorType, value: f32) -> Rgb565 {
+    if sensor == SensorType::Wbgt {
+        get_heat_risk_color(HeatRisk::from_wbgt(value)).0
+    }
+    else if sensor == SensorType::AbsoluteHumidity && Dryness::from_absolute(value) != Dryness::Moist {
+        get_dryness_color(Dryness::from_absolute(value))
+    }
+    else if is_alert(sensor, value) {
+        Rgb565::RED
+    }
+    else {
+        get_color(sensor)
+    }
+}
+
+// 行の数値の表示色
+// 相対湿度は乾燥の目安になる絶対湿度で色を決める
+pub fn get_row_color(sensor: SensorType, value: f32, history: &DataSet) -> Rgb565 {
+    match history.get_latest(SensorType::AbsoluteHumidity).map(Dryness::from_absolute) {
+        Some(dryness) if sensor == SensorType::Humidity && dryness != Dryness::Moist => get_dryness_color(dryness),
+        _ => get_value_color(sensor, value)
+    }
+}
+
+pub fn get_dryness_color(dryness: Dryness) -> Rgb565 {
+    match dryness {
+        Dryness::Dry => Rgb565::RED,
+        Dryness::Caution => Rgb565::YELLOW,
+        Dryness::Moist => get_color(SensorType::Humidity)
     }
 }
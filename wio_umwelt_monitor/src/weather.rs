@@ -0,0 +1,149 @@
+//! pressure tendency and weather forecast for wio_umwelt_monitor
+
+// 気圧変化傾向を求める期間[s]
+pub const TENDENCY_WINDOW: u32 = 3 * 3600;
+// 変化を求める両端の値はこの期間[s]の平均とする（ノイズを抑える）
+pub const TENDENCY_AVERAGE: u32 = 600;
+// 3時間の変化がこれ未満なら横ばい、これ以上なら急な変化とする[hPa]（英国気象庁の区分）
+const STEADY_CHANGE: f32 = 1.6;
+const FAST_CHANGE: f32 = 3.6;
+
+// Zambretti予報の文（A～Z）
+const ZAMBRETTI_TEXTS: [&str; 26] = [
+    "Settled fine",
+    "Fine weather",
+    "Becoming fine",
+    "Fine, becoming less settled",
+    "Fine, possible showers",
+    "Fairly fine, improving",
+    "Fairly fine, possible showers early",
+    "Fairly fine, showery later",
+    "Showery early, improving",
+    "Changeable, mending",
+    "Fairly fine, showers likely",
+    "Rather unsettled, clearing later",
+    "Unsettled, probably improving",
+    "Showery, bright intervals",
+    "Showery, becoming less settled",
+    "Changeable, some rain",
+    "Unsettled, short fine intervals",
+    "Unsettled, rain later",
+    "Unsettled, some rain",
+    "Mostly very unsettled",
+    "Occasional rain, worse later",
+    "Rain at times, very unsettled",
+    "Rain at frequent intervals",
+    "Rain, very unsettled",
+    "Stormy, may improve",
+    "Stormy, much rain"
+];
+
+// 気圧傾向ごとの予報番号から予報の文字への対応
+const ZAMBRETTI_FALLING: &[u8] = b"ABDHORUVX";
+const ZAMBRETTI_STEADY: &[u8] = b"ABEKNPSWXZ";
+const ZAMBRETTI_RISING: &[u8] = b"ABCFGIJLMQTYZ";
+
+// 3時間の気圧変化傾向
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tendency {
+    RisingFast,
+    Rising,
+    Steady,
+    Falling,
+    FallingFast
+}
+
+// 天気の大まかな分類（表示の色に使う）
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WeatherClass {
+    Fine,
+    Changeable,
+    Rain,
+    Stormy
+}
+
+// Zambretti予報（'A'～'Z'）
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Zambretti(u8);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PressureOutlook {
+    // 3時間の変化[hPa]
+    pub change: f32,
+    pub tendency: Tendency,
+    pub forecast: Zambretti,
+    // 急な気圧低下（荒天の兆し）
+    pub storm: bool
+}
+
+impl Tendency {
+    pub fn from_change(change: f32) -> Tendency {
+        if FAST_CHANGE <= change {
+            Tendency::RisingFast
+        }
+        else if STEADY_CHANGE <= change {
+            Tendency::Rising
+        }
+        else if -STEADY_CHANGE < change {
+            Tendency::Steady
+        }
+        else if -FAST_CHANGE < change {
+            Tendency::Falling
+        }
+        else {
+            Tendency::FallingFast
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tendency::RisingFast => "rising fast",
+            Tendency::Rising => "rising",
+            Tendency::Steady => "steady",
+            Tendency::Falling => "falling",
+            Tendency::FallingFast => "falling fast"
+        }
+    }
+}
+
+impl Zambretti {
+    // sea_levelは海面気圧[hPa]（Negretti and Zambrettiの近似式）
+    pub fn new(sea_level: f32, tendency: Tendency) -> Zambretti {
+        // 予報番号は下降が1～9、横ばいが10～19、上昇が20～32
+        let (z, first, table) = match tendency {
+            Tendency::RisingFast | Tendency::Rising => (185.0 - 0.16 * sea_level, 20, ZAMBRETTI_RISING),
+            Tendency::Steady => (144.0 - 0.13 * sea_level, 10, ZAMBRETTI_STEADY),
+            Tendency::Falling | Tendency::FallingFast => (127.0 - 0.12 * sea_level, 1, ZAMBRETTI_FALLING)
+        };
+
+        let index = (libm::roundf(z) as i32 - first).clamp(0, table.len() as i32 - 1);
+        Zambretti(table[index as usize])
+    }
+
+    pub fn text(&self) -> &'static str {
+        ZAMBRETTI_TEXTS[(self.0 - b'A') as usize]
+    }
+
+    pub fn class(&self) -> WeatherClass {
+        match self.0 {
+            b'A'..=b'F' => WeatherClass::Fine,
+            b'G'..=b'M' => WeatherClass::Changeable,
+            b'N'..=b'X' => WeatherClass::Rain,
+            _ => WeatherClass::Stormy
+        }
+    }
+}
+
+impl PressureOutlook {
+    // changeは3時間の変化[hPa]、storm_dropはこれ以上の低下を荒天の兆しとする値[hPa]
+    pub fn new(sea_level: f32, change: f32, storm_drop: f32) -> PressureOutlook {
+        let tendency = Tendency::from_change(change);
+
+        PressureOutlook {
+            change,
+            tendency,
+            forecast: Zambretti::new(sea_level, tendency),
+            storm: change <= -storm_drop
+        }
+    }
+}
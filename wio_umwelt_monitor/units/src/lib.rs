@@ -0,0 +1,161 @@
+//! physical quantities for environmental sensors
+
+#![no_std]
+
+
+// 温度 [℃]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Celsius(pub f32);
+
+// 温度 [°F]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Fahrenheit(pub f32);
+
+// 相対湿度 [%RH]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct RelativeHumidity(pub f32);
+
+// 濃度 [ppm]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Ppm(pub f32);
+
+// 気圧 [hPa]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Hectopascal(pub f32);
+
+// 気圧 [kPa]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Kilopascal(pub f32);
+
+// 気圧 [inHg]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct InchOfMercury(pub f32);
+
+// 気圧 [mmHg]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct MillimeterOfMercury(pub f32);
+
+// 絶対湿度（容積絶対湿度） [g/m3]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct AbsoluteHumidity(pub f32);
+
+// 不快指数（温湿度指数、THI）
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct DiscomfortIndex(pub f32);
+
+const HECTOPASCAL_PER_INCH_OF_MERCURY: f32 = 33.863_89;
+const HECTOPASCAL_PER_MILLIMETER_OF_MERCURY: f32 = 1.333_224;
+
+// 国際標準大気の係数（海面更正に使う）
+const STANDARD_LAPSE_FACTOR: f32 = 2.255_77e-5;
+const STANDARD_PRESSURE_EXPONENT: f32 = 5.255_88;
+
+// Magnus式の係数（Alduchov and Eskridge, 1996）
+const MAGNUS_A: f32 = 6.1094;
+const MAGNUS_B: f32 = 17.625;
+const MAGNUS_C: f32 = 243.04;
+
+// 水蒸気の気体定数から求めた係数 [g K / (m3 hPa)]
+const ABSOLUTE_HUMIDITY_FACTOR: f32 = 216.7;
+const ZERO_CELSIUS_IN_KELVIN: f32 = 273.15;
+
+impl Celsius {
+    pub fn to_fahrenheit(self) -> Fahrenheit {
+        Fahrenheit(self.0 * 9.0 / 5.0 + 32.0)
+    }
+
+    pub fn from_fahrenheit(fahrenheit: Fahrenheit) -> Celsius {
+        Celsius((fahrenheit.0 - 32.0) * 5.0 / 9.0)
+    }
+}
+
+impl Hectopascal {
+    pub fn to_kilopascal(self) -> Kilopascal {
+        Kilopascal(self.0 / 10.0)
+    }
+
+    pub fn to_inch_of_mercury(self) -> InchOfMercury {
+        InchOfMercury(self.0 / HECTOPASCAL_PER_INCH_OF_MERCURY)
+    }
+
+    pub fn to_millimeter_of_mercury(self) -> MillimeterOfMercury {
+        MillimeterOfMercury(self.0 / HECTOPASCAL_PER_MILLIMETER_OF_MERCURY)
+    }
+
+    // 標高altitude[m]での気圧を海面気圧に換算する（国際標準大気を仮定）
+    pub fn to_sea_level(self, altitude: f32) -> Hectopascal {
+        Hectopascal(self.0 / libm::powf(1.0 - STANDARD_LAPSE_FACTOR * altitude, STANDARD_PRESSURE_EXPONENT))
+    }
+}
+
+impl RelativeHumidity {
+    // 露点温度（Magnus式）
+    pub fn dew_point(self, temperature: Celsius) -> Celsius {
+        // 0%では対数が発散するので下限を設ける
+        let rh = self.0.clamp(0.1, 100.0);
+        let gamma = libm::logf(rh / 100.0) + MAGNUS_B * temperature.0 / (MAGNUS_C + temperature.0);
+
+        Celsius(MAGNUS_C * gamma / (MAGNUS_B - gamma))
+    }
+
+    // 温度measuredで測った相対湿度を温度actualでの値に換算する（水蒸気の量は変わらないとする）
+    pub fn at_temperature(self, measured: Celsius, actual: Celsius) -> RelativeHumidity {
+        let ratio = saturation_vapor_pressure(measured).0 / saturation_vapor_pressure(actual).0;
+
+        RelativeHumidity((self.0 * ratio).clamp(0.0, 100.0))
+    }
+
+    // 絶対湿度
+    pub fn to_absolute(self, temperature: Celsius) -> AbsoluteHumidity {
+        let vapor_pressure = self.0 / 100.0 * saturation_vapor_pressure(temperature).0;
+
+        AbsoluteHumidity(ABSOLUTE_HUMIDITY_FACTOR * vapor_pressure / (ZERO_CELSIUS_IN_KELVIN + temperature.0))
+    }
+
+    // 不快指数
+    pub fn discomfort_index(self, temperature: Celsius) -> DiscomfortIndex {
+        let t = temperature.0;
+
+        DiscomfortIndex(0.81 * t + 0.01 * self.0 * (0.99 * t - 14.3) + 46.3)
+    }
+
+    // 屋内の暑さ指数(WBGT)の推定値
+    // 環境省の推定式で日射量と風速を0としたもの
+    pub fn wbgt_indoor(self, temperature: Celsius) -> Celsius {
+        let t = temperature.0;
+        let rh = self.0;
+
+        Celsius(0.735 * t + 0.0374 * rh + 0.002_92 * t * rh - 4.064)
+    }
+
+    // 暑さ指数（米国気象局のheat index、Rothfuszの回帰式）
+    pub fn heat_index(self, temperature: Celsius) -> Celsius {
+        let t = temperature.to_fahrenheit().0;
+        let rh = self.0;
+
+        // まず簡易式で求め、80F以上のときだけ回帰式を使う
+        let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+        if (simple + t) / 2.0 < 80.0 {
+            return Celsius::from_fahrenheit(Fahrenheit(simple));
+        }
+
+        let mut hi = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+            - 0.224_755_4 * t * rh - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh - 0.000_001_99 * t * t * rh * rh;
+
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            hi -= (13.0 - rh) / 4.0 * libm::sqrtf((17.0 - libm::fabsf(t - 95.0)) / 17.0);
+        }
+        else if 85.0 < rh && (80.0..=87.0).contains(&t) {
+            hi += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+        }
+
+        Celsius::from_fahrenheit(Fahrenheit(hi))
+    }
+}
+
+// 飽和水蒸気圧（Magnus式）
+pub fn saturation_vapor_pressure(temperature: Celsius) -> Hectopascal {
+    Hectopascal(MAGNUS_A * libm::expf(MAGNUS_B * temperature.0 / (MAGNUS_C + temperature.0)))
+}
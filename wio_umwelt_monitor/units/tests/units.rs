@@ -0,0 +1,80 @@
+// ホスト上で実行する
+// cargo test --target x86_64-unknown-linux-gnu
+
+use units::*;
+
+fn assert_near(actual: f32, expected: f32, tolerance: f32) {
+    assert!((actual - expected).abs() <= tolerance, "{} is not near {}", actual, expected);
+}
+
+#[test]
+fn temperature_conversion() {
+    assert_eq!(Celsius(0.0).to_fahrenheit(), Fahrenheit(32.0));
+    assert_eq!(Celsius(100.0).to_fahrenheit(), Fahrenheit(212.0));
+    assert_near(Celsius::from_fahrenheit(Fahrenheit(98.6)).0, 37.0, 0.001);
+    assert_near(Celsius::from_fahrenheit(Celsius(21.5).to_fahrenheit()).0, 21.5, 0.001);
+}
+
+// 標準大気圧の各単位での値
+#[test]
+fn pressure_conversion() {
+    let standard = Hectopascal(1013.25);
+
+    assert_near(standard.to_kilopascal().0, 101.325, 0.001);
+    assert_near(standard.to_inch_of_mercury().0, 29.921, 0.001);
+    assert_near(standard.to_millimeter_of_mercury().0, 760.0, 0.01);
+}
+
+// 標高0mでは海面気圧はそのまま、高いところほど大きく換算する
+#[test]
+fn sea_level_pressure() {
+    assert_eq!(Hectopascal(1000.0).to_sea_level(0.0), Hectopascal(1000.0));
+    // 標高100mでは約12hPa低く出る
+    assert_near(Hectopascal(1001.3).to_sea_level(100.0).0, 1013.3, 0.2);
+}
+
+#[test]
+fn dew_point() {
+    assert_near(RelativeHumidity(50.0).dew_point(Celsius(25.0)).0, 13.86, 0.01);
+    // 飽和していれば気温と同じ
+    assert_near(RelativeHumidity(100.0).dew_point(Celsius(20.0)).0, 20.0, 0.01);
+    // 0%でも発散しない
+    assert!(RelativeHumidity(0.0).dew_point(Celsius(20.0)).0.is_finite());
+}
+
+#[test]
+fn absolute_humidity() {
+    assert_near(RelativeHumidity(50.0).to_absolute(Celsius(25.0)).0, 11.49, 0.01);
+    assert_eq!(RelativeHumidity(0.0).to_absolute(Celsius(25.0)).0, 0.0);
+}
+
+// 水蒸気の量が同じなら、温度が上がると相対湿度は下がる
+#[test]
+fn humidity_at_other_temperature() {
+    assert_near(RelativeHumidity(50.0).at_temperature(Celsius(25.0), Celsius(25.0)).0, 50.0, 0.001);
+    assert_near(RelativeHumidity(50.0).at_temperature(Celsius(25.0), Celsius(30.0)).0, 37.31, 0.01);
+    // 結露するほど冷えても100%を超えない
+    assert_eq!(RelativeHumidity(90.0).at_temperature(Celsius(25.0), Celsius(10.0)).0, 100.0);
+}
+
+#[test]
+fn discomfort_index() {
+    assert_near(RelativeHumidity(60.0).discomfort_index(Celsius(25.0)).0, 72.82, 0.01);
+}
+
+// 米国気象局の表の値（90°F、70%で106°F）
+#[test]
+fn heat_index() {
+    let temperature = Celsius::from_fahrenheit(Fahrenheit(90.0));
+
+    assert_near(RelativeHumidity(70.0).heat_index(temperature).to_fahrenheit().0, 105.9, 0.1);
+    // 涼しいときは簡易式を使い、気温に近い値になる
+    assert_near(RelativeHumidity(50.0).heat_index(Celsius(20.0)).0, 19.6, 0.5);
+}
+
+// 0°Cでは約6.11hPa、温度が上がると増える
+#[test]
+fn saturation_vapor_pressure_grows_with_temperature() {
+    assert_near(saturation_vapor_pressure(Celsius(0.0)).0, 6.11, 0.01);
+    assert!(saturation_vapor_pressure(Celsius(30.0)).0 > saturation_vapor_pressure(Celsius(20.0)).0);
+}